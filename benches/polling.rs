@@ -0,0 +1,119 @@
+//! Measures how many command round trips per second the crate can do
+//! when the bus itself costs nothing.
+
+#[macro_use]
+extern crate criterion;
+extern crate pn532;
+
+use criterion::{Criterion, Throughput};
+use pn532::PN532;
+use pn532::bus::{BusWrite, WaitRead};
+use std::io;
+
+// Answers every command immediately with ACK followed by a response
+// consisting of the response code and `payload`.
+// `noise` bytes are put before the response frame to exercise the parser.
+struct Loopback {
+    payload: Vec<u8>,
+    noise: usize,
+    ack_pending: bool,
+    response: Vec<u8>,
+}
+
+impl Loopback {
+    fn new(payload: &[u8], noise: usize) -> Self {
+        Loopback {
+            payload: payload.to_vec(),
+            noise,
+            ack_pending: false,
+            response: Vec::new(),
+        }
+    }
+}
+
+impl BusWrite for Loopback {
+    type WriteError = io::Error;
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), io::Error> {
+        // ACK sent by host
+        if buf.len() < 7 {
+            return Ok(());
+        }
+
+        let mut data = vec![buf[6].wrapping_add(1)];
+        data.extend_from_slice(&self.payload);
+
+        let len = data.len() as u8 + 1;
+        let checksum = data.iter().fold(0xD5u8, |a, b| a.wrapping_add(*b));
+        self.response.clear();
+        self.response.push(0x01);
+        self.response.extend((0..self.noise).map(|i| i as u8 | 0x01));
+        self.response.extend_from_slice(&[0x00, 0x00, 0xFF, len, 0u8.wrapping_sub(len), 0xD5]);
+        self.response.extend_from_slice(&data);
+        self.response.push(0u8.wrapping_sub(checksum));
+        self.response.push(0x00);
+        self.ack_pending = true;
+        Ok(())
+    }
+}
+
+impl WaitRead for Loopback {
+    type ReadError = io::Error;
+
+    fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let src: &[u8] = if self.ack_pending {
+            self.ack_pending = false;
+            &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]
+        } else {
+            &self.response
+        };
+        let len = std::cmp::min(buf.len(), src.len());
+        buf[..len].copy_from_slice(&src[..len]);
+        Ok(buf.len())
+    }
+}
+
+fn round_trip(c: &mut Criterion) {
+    let mut group = c.benchmark_group("round_trip");
+    group.throughput(Throughput::Elements(1));
+
+    let mut pn532 = PN532::new(Loopback::new(&[0x32, 0x01, 0x06, 0x07], 0));
+    group.bench_function("firmware_version", |b| b.iter(|| pn532.firmware_version().unwrap()));
+
+    let mut pn532 = PN532::new(Loopback::new(&[0x00, 0x04, 0x00], 0));
+    let mut data_in = [0u8; 16];
+    group.bench_function("communicate_thru", |b| b.iter(|| pn532.communicate_thru(&[0x26], &mut data_in).unwrap()));
+
+    group.finish();
+}
+
+fn response_parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("response_parser");
+    group.throughput(Throughput::Elements(1));
+
+    for noise in &[0, 8, 16] {
+        let mut pn532 = PN532::new(Loopback::new(&[0x32, 0x01, 0x06, 0x07], *noise));
+        group.bench_function(format!("noise_{}", noise), |b| b.iter(|| pn532.firmware_version().unwrap()));
+    }
+
+    group.finish();
+}
+
+// Frames close to the maximum normal frame length in both directions,
+// so the cost of copying the data around shows up.
+fn large_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_frame");
+
+    let data_out = [0x5Au8; 240];
+    let mut payload = vec![0x00];
+    payload.extend_from_slice(&[0xA5u8; 240]);
+    let mut pn532 = PN532::new(Loopback::new(&payload, 0));
+    let mut data_in = [0u8; 262];
+    group.throughput(Throughput::Bytes((data_out.len() + payload.len() - 1) as u64));
+    group.bench_function("transceive", |b| b.iter(|| pn532.transceive(1, &data_out, &mut data_in).unwrap()));
+
+    group.finish();
+}
+
+criterion_group!(benches, round_trip, response_parser, large_frame);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Feeds arbitrary bytes received from the bus into the response parser.
+//! Parsing must never panic, whatever the device (or the card behind it) sends.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pn532::bus::{BusWrite, WaitRead};
+use pn532::PN532;
+use std::io;
+
+// Acknowledges the command, then returns fuzzer input as the response.
+struct Replay<'a> {
+    data: &'a [u8],
+    acked: bool,
+}
+
+impl<'a> BusWrite for Replay<'a> {
+    type WriteError = io::Error;
+
+    fn write(&mut self, _: &[u8]) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+impl<'a> WaitRead for Replay<'a> {
+    type ReadError = io::Error;
+
+    fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let src: &[u8] = if self.acked {
+            self.data
+        } else {
+            self.acked = true;
+            &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]
+        };
+        for b in buf.iter_mut() {
+            *b = 0;
+        }
+        let len = std::cmp::min(buf.len(), src.len());
+        buf[..len].copy_from_slice(&src[..len]);
+        Ok(buf.len())
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut pn532 = PN532::new(Replay { data, acked: false });
+    let _ = pn532.firmware_version();
+
+    let mut pn532 = PN532::new(Replay { data, acked: false });
+    let mut data_in = [0u8; 64];
+    let _ = pn532.communicate_thru(&[0x26], &mut data_in);
+});
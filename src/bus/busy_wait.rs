@@ -1,7 +1,7 @@
 //! This module contains types and routines for busy waiting strategy
 //! of communicating with PN532.
 
-use super::{BusRead, BusWrite, WaitRead, WaitReadTimeout};
+use super::{BusRead, BusWrite, BusKind, WaitRead, WaitReadTimeout, CancelToken};
 use error::{WaitResult, WaitError};
 
 /// It might be necessary to change this on other platforms.
@@ -34,14 +34,16 @@ pub trait Timer {
 pub struct BusyWait<D: BusRead + BusWrite, T: Timer> {
     device: D,
     delay: T::Duration,
+    cancel: Option<CancelToken>,
 }
 
 impl<D: BusRead + BusWrite, T: Timer> BusyWait<D, T> where T::Duration: FromMilliseconds {
     /// Enables busy waiting with default delay.
     pub fn new(device: D) -> Self {
         BusyWait {
-            device: device,
-            delay: T::Duration::from_milliseconds(190)
+            device,
+            delay: T::Duration::from_milliseconds(190),
+            cancel: None,
         }
     }
 }
@@ -50,18 +52,36 @@ impl<D: BusRead + BusWrite, T: Timer> BusyWait<D, T> {
     /// Enables busy waiting with custom delay.
     pub fn with_delay(device: D, delay: T::Duration) -> Self {
         BusyWait {
-            device: device,
-            delay: delay
+            device,
+            delay,
+            cancel: None,
         }
     }
 
+    /// Enables busy waiting with custom delay, which can be cancelled by `cancel`.
+    ///
+    /// Every wait with timeout then checks the token before each iteration and returns
+    /// `WaitError::Cancelled` once it's cancelled, so a worker thread polling for tags
+    /// can be stopped without waiting for the timeout. `wait_read` can't report
+    /// the cancellation, wrap the bus in `WithTimeout` to make all waits cancellable.
+    pub fn with_cancel(device: D, delay: T::Duration, cancel: CancelToken) -> Self {
+        BusyWait {
+            device,
+            delay,
+            cancel: Some(cancel),
+        }
+    }
+
+    // Returns true if the token given to `with_cancel` was cancelled.
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
     // One wait iteration
     fn wait_iter(&mut self, buf: &mut [u8]) -> Result<bool, D::ReadError> {
         T::wait(&self.delay);
 
-        try!(self.device.read(buf));
-
-        Ok(buf[0] & 1 == 1)
+        self.device.read_if_ready(buf)
     }
 }
 
@@ -70,7 +90,7 @@ impl<D: BusRead + BusWrite, T: Timer> WaitRead for BusyWait<D, T> {
 
     fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
         loop {
-            if try!(self.wait_iter(buf)) {
+            if self.wait_iter(buf)? {
                 return Ok(buf.len());
             }
         }
@@ -80,15 +100,38 @@ impl<D: BusRead + BusWrite, T: Timer> WaitRead for BusyWait<D, T> {
 impl<D: BusRead + BusWrite, T: Timer> WaitReadTimeout for BusyWait<D, T> {
     type Duration = T::Duration;
 
-    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError> {
+    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        let start_time = T::now();
+        loop {
+            if self.is_cancelled() {
+                return Err(WaitError::Cancelled);
+            }
+
+            if self.wait_iter(buf)? {
+                return Ok(buf.len());
+            }
+
+            let elapsed = start_time.elapsed();
+            if elapsed > timeout {
+                return Err(WaitError::Timeout(elapsed));
+            }
+        }
+    }
+
+    fn wait_read_cancellable(&mut self, buf: &mut [u8], timeout: Self::Duration, cancel: &CancelToken) -> WaitResult<usize, Self::ReadError, Self::Duration> {
         let start_time = T::now();
         loop {
-            if try!(self.wait_iter(buf)) {
+            if cancel.is_cancelled() || self.is_cancelled() {
+                return Err(WaitError::Cancelled);
+            }
+
+            if self.wait_iter(buf)? {
                 return Ok(buf.len());
             }
 
-            if start_time.elapsed() > timeout {
-                return Err(WaitError::Timeout);
+            let elapsed = start_time.elapsed();
+            if elapsed > timeout {
+                return Err(WaitError::Timeout(elapsed));
             }
         }
     }
@@ -100,11 +143,26 @@ impl <D: BusRead + BusWrite, T: Timer> BusWrite for BusyWait<D, T> {
     fn write(&mut self, buf: &[u8]) -> Result<(), Self::WriteError> {
         self.device.write(buf)
     }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), Self::WriteError> {
+        self.device.write_vectored(bufs)
+    }
+
+    fn max_write_len(&self) -> usize {
+        self.device.max_write_len()
+    }
+
+    fn wakeup(&mut self) -> Result<(), Self::WriteError> {
+        self.device.wakeup()
+    }
+
+    fn kind(&self) -> BusKind {
+        self.device.kind()
+    }
 }
 
 /// Implements appropriate traits for std types.
-/// TODO: make compilation of this module conditional.
-/// (This module should be disabled in case of no_std.)
+#[cfg(feature = "std")]
 mod std_impls {
     use super::{Milliseconds, FromMilliseconds, Timer};
     use ::std::time::{Duration, Instant};
@@ -0,0 +1,67 @@
+//! Cancellation of waiting for PN532 from another thread.
+
+#[cfg(feature = "std")]
+use ::std::sync::Arc;
+use ::core::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag which makes waiting for PN532 return `WaitError::Cancelled`.
+///
+/// Clones share the same flag, so one clone can be passed to the thread communicating
+/// with PN532 and other one kept for cancelling.
+#[derive(Clone, Debug)]
+pub struct CancelToken {
+    cancelled: Flag,
+}
+
+// Without std there's no `Arc`, so the flag must be static there.
+#[derive(Clone, Debug)]
+enum Flag {
+    #[cfg(feature = "std")]
+    Shared(Arc<AtomicBool>),
+    Static(&'static AtomicBool),
+}
+
+impl CancelToken {
+    #[cfg(feature = "std")]
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Creates token backed by `flag`, useful without std, e.g. for cancelling from interrupt handler.
+    pub fn from_static(flag: &'static AtomicBool) -> Self {
+        CancelToken {
+            cancelled: Flag::Static(flag),
+        }
+    }
+
+    fn flag(&self) -> &AtomicBool {
+        match self.cancelled {
+            #[cfg(feature = "std")]
+            Flag::Shared(ref flag) => flag,
+            Flag::Static(flag) => flag,
+        }
+    }
+
+    /// Requests cancellation of current and all future waits until `reset` is called.
+    pub fn cancel(&self) {
+        self.flag().store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag().load(Ordering::SeqCst)
+    }
+
+    /// Clears the flag, so the token can be used again.
+    pub fn reset(&self) {
+        self.flag().store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for CancelToken {
+    fn default() -> Self {
+        CancelToken {
+            cancelled: Flag::Shared(Arc::new(AtomicBool::new(false))),
+        }
+    }
+}
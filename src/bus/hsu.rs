@@ -0,0 +1,288 @@
+//! High speed UART bus.
+//!
+//! Unlike I2C and SPI, HSU has no ready status, PN532 simply starts sending the frame
+//! when it's ready. The frame is read byte by byte and prefixed with the ready status,
+//! so it looks the same as a frame read from the other buses.
+
+use super::{BusWrite, BusKind, WaitRead, WaitReadTimeout, CancelToken, HSU_WAKEUP};
+use super::busy_wait::{FromMilliseconds, Timer};
+use ::error::{WaitResult, WaitError};
+use ::std::io::{self, Read, Write};
+
+/// PN532 connected over HSU.
+///
+/// The serial port `S` should be configured with a short read timeout (or as non-blocking),
+/// so that the waiting can be interrupted. Reads returning `Ok(0)`, `TimedOut`
+/// or `WouldBlock` are treated as no data being available yet.
+///
+/// Once the frame starts arriving, each byte must follow the previous one within
+/// `inter_byte_timeout`, otherwise the read fails with `WaitError::Timeout`.
+/// This prevents waiting forever for the rest of a frame that was actually line noise.
+pub struct Hsu<S: Read + Write, T: Timer> {
+    port: S,
+    inter_byte_timeout: T::Duration,
+}
+
+impl<S: Read + Write, T: Timer> Hsu<S, T> where T::Duration: FromMilliseconds {
+    /// Wraps the serial port using default inter-byte timeout of 20 ms.
+    pub fn new(port: S) -> Self {
+        Hsu {
+            port,
+            inter_byte_timeout: T::Duration::from_milliseconds(20),
+        }
+    }
+}
+
+impl<S: Read + Write, T: Timer> Hsu<S, T> {
+    /// Wraps the serial port using custom inter-byte timeout.
+    pub fn with_inter_byte_timeout(port: S, inter_byte_timeout: T::Duration) -> Self {
+        Hsu {
+            port,
+            inter_byte_timeout,
+        }
+    }
+
+    pub fn set_inter_byte_timeout(&mut self, inter_byte_timeout: T::Duration) {
+        self.inter_byte_timeout = inter_byte_timeout;
+    }
+
+    pub fn into_inner(self) -> S {
+        self.port
+    }
+
+    // Returns `Ok(None)` if no byte is available yet.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8];
+        match self.port.read(&mut byte) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(byte[0])),
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::Interrupted => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Waits for the first byte of the frame until `expired` returns some error.
+    fn wait_first<F>(&mut self, mut expired: F) -> WaitResult<u8, io::Error, T::Duration> where F: FnMut() -> Option<WaitError<io::Error, T::Duration>> {
+        loop {
+            if let Some(byte) = self.read_byte()? {
+                return Ok(byte);
+            }
+            if let Some(err) = expired() {
+                return Err(err);
+            }
+        }
+    }
+
+    // Reads the rest of the frame which started with `first`, storing it after the ready status.
+    fn read_frame(&mut self, first: u8, buf: &mut [u8]) -> WaitResult<usize, io::Error, T::Duration> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = 0x01;
+        let mut len = 1;
+        let mut byte = first;
+        // Position of LCS, known once start code is found.
+        let mut lcs_pos = None;
+        // Number of bytes left in the frame, known once LCS is read.
+        let mut remaining = None;
+        loop {
+            if len < buf.len() {
+                buf[len] = byte;
+            }
+            len += 1;
+
+            match (lcs_pos, remaining) {
+                (None, _) if len >= 3 && buf[(len - 2)..len] == [0x00, 0xFF] => lcs_pos = Some(len + 1),
+                // ACK and NACK only have the postamble left, other frames also data and DCS.
+                (Some(pos), None) if pos == len - 1 => remaining = match (buf[len - 2], buf[len - 1]) {
+                    (0x00, 0xFF) | (0xFF, 0x00) => Some(1),
+                    (frame_len, _) => Some(frame_len as usize + 2),
+                },
+                (_, Some(n)) => remaining = Some(n - 1),
+                _ => (),
+            }
+
+            if remaining == Some(0) || len >= buf.len() {
+                return Ok(len.min(buf.len()));
+            }
+
+            let start_time = T::now();
+            byte = loop {
+                if let Some(byte) = self.read_byte()? {
+                    break byte;
+                }
+                let elapsed = start_time.elapsed();
+                if elapsed > self.inter_byte_timeout {
+                    return Err(WaitError::Timeout(elapsed));
+                }
+            };
+        }
+    }
+}
+
+impl<S: Read + Write, T: Timer> WaitRead for Hsu<S, T> {
+    type ReadError = io::Error;
+
+    // Waits for the frame indefinitely, but not for the rest of an incomplete frame.
+    fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
+        let res = self.wait_first(|| None).and_then(|first| self.read_frame(first, buf));
+        res.map_err(|e| match e {
+            WaitError::OtherError(e) => e,
+            _ => io::Error::new(io::ErrorKind::TimedOut, "PN532 stopped sending in the middle of frame"),
+        })
+    }
+}
+
+impl<S: Read + Write, T: Timer> WaitReadTimeout for Hsu<S, T> {
+    type Duration = T::Duration;
+
+    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        let start_time = T::now();
+        let first = self.wait_first(|| {
+            let elapsed = start_time.elapsed();
+            if elapsed > timeout {
+                Some(WaitError::Timeout(elapsed))
+            } else {
+                None
+            }
+        })?;
+        self.read_frame(first, buf)
+    }
+
+    fn wait_read_cancellable(&mut self, buf: &mut [u8], timeout: Self::Duration, cancel: &CancelToken) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        let start_time = T::now();
+        let first = self.wait_first(|| {
+            if cancel.is_cancelled() {
+                return Some(WaitError::Cancelled);
+            }
+            let elapsed = start_time.elapsed();
+            if elapsed > timeout {
+                Some(WaitError::Timeout(elapsed))
+            } else {
+                None
+            }
+        })?;
+        self.read_frame(first, buf)
+    }
+}
+
+impl<S: Read + Write, T: Timer> BusWrite for Hsu<S, T> {
+    type WriteError = io::Error;
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Self::WriteError> {
+        self.port.write_all(buf)?;
+        self.port.flush()
+    }
+
+    // UART has no transactions, so the parts can be written one by one without copying.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), Self::WriteError> {
+        for buf in bufs {
+            self.port.write_all(buf)?;
+        }
+        self.port.flush()
+    }
+
+    fn wakeup(&mut self) -> Result<(), Self::WriteError> {
+        self.write(&HSU_WAKEUP)
+    }
+
+    fn kind(&self) -> BusKind {
+        BusKind::Hsu
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Hsu;
+    use ::bus::{WaitReadTimeout, BusWrite};
+    use ::error::WaitError;
+    use ::std::collections::VecDeque;
+    use ::std::io::{self, Read, Write};
+    use ::std::time::{Duration, Instant};
+
+    // Serial port with read timeout, which has nothing more to say after `input`.
+    struct Port {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Port {
+        fn new(input: &[u8]) -> Self {
+            Port {
+                input: input.iter().cloned().collect(),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Port {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.input.pop_front() {
+                Some(byte) => {
+                    buf[0] = byte;
+                    Ok(1)
+                },
+                None => Err(io::Error::new(io::ErrorKind::TimedOut, "no data")),
+            }
+        }
+    }
+
+    impl Write for Port {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_frames() {
+        let ack = [0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+        let frame = [0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x15, 0x16, 0x00];
+        let mut input = ack.to_vec();
+        input.extend_from_slice(&frame);
+        let mut hsu = Hsu::<_, Instant>::new(Port::new(&input));
+
+        let mut buf = [0u8; 32];
+        let len = hsu.wait_read_timeout(&mut buf, Duration::from_millis(100)).unwrap();
+        assert_eq!(buf[0], 0x01);
+        assert_eq!(&buf[1..len], &ack);
+
+        let len = hsu.wait_read_timeout(&mut buf, Duration::from_millis(100)).unwrap();
+        assert_eq!(&buf[1..len], &frame);
+    }
+
+    #[test]
+    fn inter_byte_timeout() {
+        let mut hsu = Hsu::<_, Instant>::with_inter_byte_timeout(Port::new(&[0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5]), Duration::from_millis(10));
+        let mut buf = [0u8; 32];
+        let begin = Instant::now();
+        assert_matches!(hsu.wait_read_timeout(&mut buf, Duration::from_secs(10)), Err(WaitError::Timeout(_)));
+        assert!(begin.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn wakeup() {
+        let mut hsu = Hsu::<_, Instant>::new(Port::new(&[]));
+        hsu.wakeup().unwrap();
+        assert_eq!(hsu.into_inner().output, &::bus::HSU_WAKEUP);
+    }
+
+    #[test]
+    fn write_vectored() {
+        let mut hsu = Hsu::<_, Instant>::new(Port::new(&[]));
+        hsu.write_vectored(&[&[0x00, 0x00, 0xFF], &[], &[0x02, 0xFE, 0xD4]]).unwrap();
+        assert_eq!(hsu.into_inner().output, &[0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD4]);
+    }
+
+    #[test]
+    fn kind() {
+        use ::bus::{BusKind, WithTimeout};
+
+        let hsu = WithTimeout::new(Hsu::<_, Instant>::new(Port::new(&[])), Duration::from_secs(1));
+        assert_eq!(hsu.kind(), BusKind::Hsu);
+    }
+}
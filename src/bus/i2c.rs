@@ -1,5 +1,7 @@
 use ::i2cdev::core::I2CDevice;
-use super::{BusRead, BusWrite};
+use super::{BusRead, BusWrite, BusKind, WaitRead, WaitReadTimeout, CancelToken};
+use super::busy_wait::{FromMilliseconds, Timer};
+use ::error::{WaitResult, WaitError};
 use ::std::path::Path;
 
 impl<D: I2CDevice> BusRead for D {
@@ -41,6 +43,146 @@ impl<D: I2CDevice> BusWrite for D {
         }
         self.write(buf)
     }
+
+    // The frame must be written in single I2C transaction, so the parts are joined
+    // unless there's just one of them.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), Self::WriteError> {
+        let mut parts = bufs.iter().filter(|buf| !buf.is_empty());
+        if let (Some(buf), None) = (parts.next(), parts.next()) {
+            return BusWrite::write(self, buf);
+        }
+
+        let mut outbuf = [0u8; 278];
+        let mut len = 0;
+        for buf in bufs {
+            outbuf[len..(len + buf.len())].copy_from_slice(buf);
+            len += buf.len();
+        }
+        BusWrite::write(self, &outbuf[..len])
+    }
+
+    // PN532 wakes up on its address, the transfer itself doesn't need to carry data.
+    fn wakeup(&mut self) -> Result<(), Self::WriteError> {
+        self.write(&[])
+    }
+
+    fn kind(&self) -> BusKind {
+        BusKind::I2C
+    }
+}
+
+/// I2C bus which waits for PN532 by polling just its status byte, pausing between the polls.
+///
+/// PN532 stretches the clock while it's processing a command, which some I2C controllers
+/// handle poorly. Reading single byte and waiting `poll_delay` between the reads keeps
+/// the bus quiet instead of hammering it with full-length reads like `BusyWait`.
+pub struct I2CBus<D: I2CDevice, T: Timer> {
+    device: D,
+    poll_delay: T::Duration,
+}
+
+impl<D: I2CDevice, T: Timer> I2CBus<D, T> where T::Duration: FromMilliseconds {
+    /// Wraps the device using default poll delay of 10 ms.
+    pub fn new(device: D) -> Self {
+        I2CBus {
+            device,
+            poll_delay: T::Duration::from_milliseconds(10),
+        }
+    }
+}
+
+impl<D: I2CDevice, T: Timer> I2CBus<D, T> {
+    /// Wraps the device using custom poll delay.
+    pub fn with_poll_delay(device: D, poll_delay: T::Duration) -> Self {
+        I2CBus {
+            device,
+            poll_delay,
+        }
+    }
+
+    pub fn set_poll_delay(&mut self, poll_delay: T::Duration) {
+        self.poll_delay = poll_delay;
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    // Reads only the status byte, PN532 sends it again at the start of the next read.
+    fn is_ready(&mut self) -> Result<bool, D::Error> {
+        let mut status = [0u8];
+        BusRead::read(&mut self.device, &mut status)?;
+        Ok(status[0] & 1 == 1)
+    }
+}
+
+impl<D: I2CDevice, T: Timer> WaitRead for I2CBus<D, T> {
+    type ReadError = D::Error;
+
+    fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
+        while !self.is_ready()? {
+            T::wait(&self.poll_delay);
+        }
+        BusRead::read(&mut self.device, buf)
+    }
+}
+
+impl<D: I2CDevice, T: Timer> WaitReadTimeout for I2CBus<D, T> {
+    type Duration = T::Duration;
+
+    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        let start_time = T::now();
+        while !self.is_ready()? {
+            let elapsed = start_time.elapsed();
+            if elapsed > timeout {
+                return Err(WaitError::Timeout(elapsed));
+            }
+            T::wait(&self.poll_delay);
+        }
+        BusRead::read(&mut self.device, buf).map_err(Into::into)
+    }
+
+    fn wait_read_cancellable(&mut self, buf: &mut [u8], timeout: Self::Duration, cancel: &CancelToken) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        let start_time = T::now();
+        while !self.is_ready()? {
+            if cancel.is_cancelled() {
+                return Err(WaitError::Cancelled);
+            }
+            let elapsed = start_time.elapsed();
+            if elapsed > timeout {
+                return Err(WaitError::Timeout(elapsed));
+            }
+            T::wait(&self.poll_delay);
+        }
+        BusRead::read(&mut self.device, buf).map_err(Into::into)
+    }
+}
+
+impl<D: I2CDevice, T: Timer> BusWrite for I2CBus<D, T> {
+    type WriteError = D::Error;
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Self::WriteError> {
+        BusWrite::write(&mut self.device, buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), Self::WriteError> {
+        BusWrite::write_vectored(&mut self.device, bufs)
+    }
+
+    fn max_write_len(&self) -> usize {
+        BusWrite::max_write_len(&self.device)
+    }
+
+    // Also gives the oscillator time to start before the next command.
+    fn wakeup(&mut self) -> Result<(), Self::WriteError> {
+        BusWrite::wakeup(&mut self.device)?;
+        T::wait(&self.poll_delay);
+        Ok(())
+    }
+
+    fn kind(&self) -> BusKind {
+        BusKind::I2C
+    }
 }
 
 #[cfg(any(target_os = "linux", target_os = "android"))]
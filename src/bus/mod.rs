@@ -4,13 +4,26 @@
 pub mod i2c;
 
 pub mod busy_wait;
+pub mod cancel;
+#[cfg(feature = "std")]
+pub mod hsu;
+pub mod timeout;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod replay;
 
 pub use self::busy_wait::BusyWait as GenericBusyWait;
+pub use self::cancel::CancelToken;
+#[cfg(feature = "std")]
+pub use self::hsu::Hsu as GenericHsu;
+pub use self::timeout::WithTimeout;
 
+#[cfg(feature = "std")]
 pub type BusyWait<T> = GenericBusyWait<T, ::std::time::Instant>;
+#[cfg(feature = "std")]
+pub type Hsu<S> = GenericHsu<S, ::std::time::Instant>;
 
-use ::error::WaitResult;
-use std::error::Error;
+use ::error::{WaitResult, WaitError};
+use ::core::error::Error;
 
 /// Abstracts reading from device over different busses (I2C, SPI, ...)
 pub trait BusRead {
@@ -21,6 +34,17 @@ pub trait BusRead {
     /// May return `Ok(n)` where `n < buf.len()` but usually it's expected
     /// to fill whole buffer.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError>;
+
+    /// Reads data from device to `buf` if it's ready, returns whether it was.
+    ///
+    /// Used by `BusyWait` for polling. The default implementation is suitable for I2C,
+    /// where every read starts with the status byte having the ready flag in its lowest bit.
+    /// Buses which find out readiness differently (e.g. SPI with its separate status read)
+    /// should override it.
+    fn read_if_ready(&mut self, buf: &mut [u8]) -> Result<bool, Self::ReadError> {
+        self.read(buf)?;
+        Ok(buf[0] & 1 == 1)
+    }
 }
 
 /// Abstracts writing to device over different busses (I2C, SPI, ...)
@@ -31,8 +55,61 @@ pub trait BusWrite {
     /// Writes data from `buf` to device.
     /// Continuation is not allowed.
     fn write(&mut self, buf: &[u8]) -> Result<(), Self::WriteError>;
+
+    /// Writes concatenation of `bufs` to device as single transfer.
+    ///
+    /// The default implementation copies the buffers to a stack buffer and calls `write`,
+    /// so their total length must not exceed 278 bytes (the length of the longest frame
+    /// together with the longest preamble).
+    /// Buses which can write the parts one by one without interrupting the transfer
+    /// should override it to avoid the copy.
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), Self::WriteError> {
+        let mut outbuf = [0u8; 278];
+        let mut len = 0;
+        for buf in bufs {
+            outbuf[len..(len + buf.len())].copy_from_slice(buf);
+            len += buf.len();
+        }
+        self.write(&outbuf[..len])
+    }
+
+    /// Maximum number of bytes that can be written at once.
+    /// Some I2C controllers can't transfer whole PN532 frame, so they should override this.
+    fn max_write_len(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Wakes PN532 up from power down, so that it accepts the next command.
+    ///
+    /// What's needed depends on the bus: HSU needs `HSU_WAKEUP` to be written,
+    /// SPI a status read and I2C any transfer addressed to PN532.
+    /// Buses which don't need anything special can keep this default no-op.
+    fn wakeup(&mut self) -> Result<(), Self::WriteError> {
+        Ok(())
+    }
+
+    /// Kind of the bus, so that code above it can account for its specifics.
+    ///
+    /// Buses wrapping other buses should return the kind of the inner one.
+    fn kind(&self) -> BusKind {
+        BusKind::Unknown
+    }
 }
 
+/// Interface through which PN532 is connected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BusKind {
+    I2C,
+    Spi,
+    Hsu,
+    /// The bus doesn't tell (e.g. it's a mock used in tests).
+    Unknown,
+}
+
+/// Bytes waking up PN532 connected over HSU: two `0x55` followed by zeroes long enough
+/// for the oscillator to start.
+pub const HSU_WAKEUP: [u8; 16] = [0x55, 0x55, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
 /// Abstracts method of waiting for device.
 pub trait WaitRead {
     /// Type returned when bus IO fails.
@@ -48,12 +125,24 @@ pub trait WaitReadTimeout: WaitRead {
     /// Blocks until device sends data or operation times out,
     /// then reads the data or returns `Err(WaitError::Timeout)`.
     /// The timeout doesn't need to be exact.
-    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError>;
+    /// On timeout, the time actually spent waiting is returned in the error.
+    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError, Self::Duration>;
+
+    /// Same as `wait_read_timeout`, but returns `Err(WaitError::Cancelled)` once `cancel` is cancelled.
+    ///
+    /// The default implementation checks `cancel` only before waiting.
+    /// Implementations which poll the device should override it to check it between polls.
+    fn wait_read_cancellable(&mut self, buf: &mut [u8], timeout: Self::Duration, cancel: &CancelToken) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        if cancel.is_cancelled() {
+            return Err(WaitError::Cancelled);
+        }
+        self.wait_read_timeout(buf, timeout)
+    }
 }
 
 
-#[cfg(test)]
-mod test {
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod test {
     use super::*;
     use ::error::WaitError;
 
@@ -63,7 +152,7 @@ mod test {
         type ReadError = ::std::io::Error;
 
         fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
-            if buf.len() < 1 {
+            if buf.is_empty() {
                 Ok(0)
             } else {
                 buf[0] = 0;
@@ -88,6 +177,62 @@ mod test {
         assert_eq!(buf[0] & 1, 0);
     }
 
+    // Reports readiness through separate status like SPI, data don't contain the ready flag.
+    pub(crate) struct StatusReady {
+        polls: usize,
+        data: &'static [u8],
+    }
+
+    impl StatusReady {
+        // Becomes ready on the third poll and then returns `data`.
+        pub(crate) fn new(data: &'static [u8]) -> Self {
+            StatusReady {
+                polls: 0,
+                data,
+            }
+        }
+    }
+
+    impl BusRead for StatusReady {
+        type ReadError = ::std::io::Error;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
+            let len = ::std::cmp::min(buf.len(), self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            for b in &mut buf[len..] {
+                *b = 0;
+            }
+            Ok(buf.len())
+        }
+
+        fn read_if_ready(&mut self, buf: &mut [u8]) -> Result<bool, Self::ReadError> {
+            self.polls += 1;
+            if self.polls < 3 {
+                return Ok(false);
+            }
+            self.read(buf)?;
+            Ok(true)
+        }
+    }
+
+    impl BusWrite for StatusReady {
+        type WriteError = ::std::io::Error;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), Self::WriteError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_custom_ready() {
+        use ::std::time::Duration;
+
+        let mut buf = [0u8; 4];
+        let mut busy_wait = BusyWait::with_delay(StatusReady::new(&[0xAA; 4]), Duration::from_millis(0));
+        assert_eq!(busy_wait.wait_read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0xAA; 4]);
+    }
+
     #[test]
     fn test_timeout() {
         use ::std::time::{Duration, Instant};
@@ -95,10 +240,44 @@ mod test {
         let begin = Instant::now();
         let mut busy_wait = BusyWait::new(NeverReady);
         match busy_wait.wait_read_timeout(&mut buf, Duration::from_secs(1)) {
-            Err(WaitError::Timeout) => (),
+            Err(WaitError::Timeout(elapsed)) => assert!(elapsed > Duration::from_secs(1)),
             Err(e) => panic!("{}", e),
             Ok(_) => panic!("Operation should've time out"),
         }
         assert!(begin.elapsed() > Duration::from_secs(1));
     }
+
+    #[test]
+    fn test_cancel() {
+        use ::std::time::{Duration, Instant};
+        use ::std::thread;
+
+        let mut buf = [0u8; 42];
+        let cancel = CancelToken::new();
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(300));
+                cancel.cancel();
+            })
+        };
+        let begin = Instant::now();
+        let mut busy_wait = BusyWait::new(NeverReady);
+        assert_matches!(busy_wait.wait_read_cancellable(&mut buf, Duration::from_secs(10), &cancel), Err(WaitError::Cancelled));
+        assert!(begin.elapsed() < Duration::from_secs(10));
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn test_cancel_injected() {
+        use ::std::time::Duration;
+
+        let mut buf = [0u8; 42];
+        let cancel = CancelToken::new();
+        let mut busy_wait = BusyWait::with_cancel(NeverReady, Duration::from_millis(1), cancel.clone());
+        assert_matches!(busy_wait.wait_read_timeout(&mut buf, Duration::from_millis(20)), Err(WaitError::Timeout(_)));
+        cancel.cancel();
+        assert_matches!(busy_wait.wait_read_timeout(&mut buf, Duration::from_secs(10)), Err(WaitError::Cancelled));
+        assert_matches!(busy_wait.wait_read_cancellable(&mut buf, Duration::from_secs(10), &CancelToken::new()), Err(WaitError::Cancelled));
+    }
 }
@@ -0,0 +1,109 @@
+//! Replaying of bus transfers captured from real device (e.g. by logic analyzer).
+//!
+//! Allows writing regression tests based on actual behavior of PN532 without the hardware.
+//! The bus should be wrapped in `BusyWait` with zero delay, so not-ready reads
+//! from the capture are polled the same way as on the real bus.
+
+use super::{BusRead, BusWrite};
+use ::std::io;
+
+/// Bytes written by the host together with the reads which followed them in the capture.
+#[derive(Debug, Copy, Clone)]
+pub struct Exchange<'a> {
+    pub written: &'a [u8],
+    /// Data returned by each read, starting with the ready status byte.
+    pub responses: &'a [&'a [u8]],
+}
+
+/// Bus replaying captured exchanges in order.
+///
+/// Panics if the host writes anything else than the capture contains or reads more
+/// than was captured, so it's meant only for tests.
+/// Captured reads shorter than the buffer are padded with zeroes.
+pub struct Replay<'a> {
+    exchanges: &'a [Exchange<'a>],
+    // Responses of the last written exchange which weren't read yet.
+    responses: &'a [&'a [u8]],
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(exchanges: &'a [Exchange<'a>]) -> Self {
+        Replay {
+            exchanges,
+            responses: &[],
+        }
+    }
+
+    /// Returns true if all captured exchanges were replayed.
+    pub fn is_done(&self) -> bool {
+        self.exchanges.is_empty() && self.responses.is_empty()
+    }
+}
+
+impl<'a> BusRead for Replay<'a> {
+    type ReadError = io::Error;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
+        use ::std::cmp::min;
+
+        let (response, rest) = self.responses.split_first().expect("read not present in the capture");
+        self.responses = rest;
+        let len = min(response.len(), buf.len());
+        buf[..len].copy_from_slice(&response[..len]);
+        for b in &mut buf[len..] {
+            *b = 0;
+        }
+        Ok(buf.len())
+    }
+}
+
+impl<'a> BusWrite for Replay<'a> {
+    type WriteError = io::Error;
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Self::WriteError> {
+        assert!(self.responses.is_empty(), "write while {} captured reads are pending", self.responses.len());
+        let (exchange, rest) = self.exchanges.split_first().expect("write not present in the capture");
+        assert_eq!(buf, exchange.written, "written bytes differ from the capture");
+        self.exchanges = rest;
+        self.responses = exchange.responses;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{Exchange, Replay};
+    use ::bus::BusyWait;
+    use ::std::time::Duration;
+    use ::PN532;
+
+    // GetFirmwareVersion captured on I2C, PN532 wasn't ready right after the command.
+    const FIRMWARE_VERSION: [Exchange<'static>; 2] = [
+        Exchange {
+            written: &[0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD4, 0x02, 0x2A, 0x00],
+            responses: &[
+                &[0x00],
+                &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00],
+                &[0x01, 0x00, 0x00, 0xFF, 0x06, 0xFA, 0xD5, 0x03, 0x32, 0x01, 0x06, 0x07, 0xE8, 0x00],
+            ],
+        },
+        Exchange {
+            written: &[0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00],
+            responses: &[],
+        },
+    ];
+
+    #[test]
+    fn replay_firmware_version() {
+        let mut pn532 = PN532::new(BusyWait::with_delay(Replay::new(&FIRMWARE_VERSION), Duration::from_millis(0)));
+        let version = pn532.firmware_version().unwrap();
+        assert_eq!((version.ic, version.ver, version.rev, version.support), (0x32, 0x01, 0x06, 0x07));
+    }
+
+    #[test]
+    #[should_panic(expected = "written bytes differ")]
+    fn replay_mismatch() {
+        let mut pn532 = PN532::new(BusyWait::with_delay(Replay::new(&FIRMWARE_VERSION), Duration::from_millis(0)));
+        let _ = pn532.general_status();
+    }
+}
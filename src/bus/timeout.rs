@@ -0,0 +1,125 @@
+//! Bounding of every wait for PN532 by default timeout.
+
+use super::{BusWrite, BusKind, WaitRead, WaitReadTimeout, CancelToken};
+use ::error::{WaitResult, WaitError};
+use ::core::fmt::Debug;
+
+/// Bus which waits for PN532 at most `timeout`, even when no timeout was requested.
+///
+/// Wrapping the bus makes every command of `PN532` bounded (`sam_configure`,
+/// `firmware_version`, ...), so a command sent to dead chip doesn't block forever.
+/// The timeout is reported as `WaitError::Timeout` inside read error.
+/// The late response may confuse the next command, so `PN532::resync` should be called
+/// after the timeout.
+pub struct WithTimeout<D: WaitReadTimeout> {
+    device: D,
+    timeout: D::Duration,
+}
+
+impl<D: WaitReadTimeout> WithTimeout<D> {
+    pub fn new(device: D, timeout: D::Duration) -> Self {
+        WithTimeout {
+            device,
+            timeout,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: D::Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D: WaitReadTimeout> WaitRead for WithTimeout<D> where D::Duration: Clone + Debug {
+    type ReadError = WaitError<D::ReadError, D::Duration>;
+
+    fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
+        self.device.wait_read_timeout(buf, self.timeout.clone())
+    }
+}
+
+impl<D: WaitReadTimeout> WaitReadTimeout for WithTimeout<D> where D::Duration: Clone + Debug {
+    type Duration = D::Duration;
+
+    // Timeout and cancellation of the inner bus are returned directly, not nested in the read error.
+    fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        self.device.wait_read_timeout(buf, timeout).map_err(|e| e.map(WaitError::OtherError))
+    }
+
+    fn wait_read_cancellable(&mut self, buf: &mut [u8], timeout: Self::Duration, cancel: &CancelToken) -> WaitResult<usize, Self::ReadError, Self::Duration> {
+        self.device.wait_read_cancellable(buf, timeout, cancel).map_err(|e| e.map(WaitError::OtherError))
+    }
+}
+
+impl<D: WaitReadTimeout + BusWrite> BusWrite for WithTimeout<D> {
+    type WriteError = D::WriteError;
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Self::WriteError> {
+        self.device.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), Self::WriteError> {
+        self.device.write_vectored(bufs)
+    }
+
+    fn max_write_len(&self) -> usize {
+        self.device.max_write_len()
+    }
+
+    fn wakeup(&mut self) -> Result<(), Self::WriteError> {
+        self.device.wakeup()
+    }
+
+    fn kind(&self) -> BusKind {
+        self.device.kind()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::WithTimeout;
+    use ::bus::{BusRead, BusWrite, BusyWait};
+    use ::error::{CommError, RecvError, WaitError};
+    use ::std::io;
+    use ::std::time::{Duration, Instant};
+    use ::PN532;
+
+    // Acknowledges every command but never sends the response.
+    struct Dead {
+        acked: bool,
+    }
+
+    impl BusRead for Dead {
+        type ReadError = io::Error;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::ReadError> {
+            buf[0] = 0;
+            if !self.acked {
+                self.acked = true;
+                let ack = [0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+                buf[..ack.len()].copy_from_slice(&ack);
+            }
+            Ok(buf.len())
+        }
+    }
+
+    impl BusWrite for Dead {
+        type WriteError = io::Error;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), Self::WriteError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn command_timeout() {
+        let bus = BusyWait::with_delay(Dead { acked: false }, Duration::from_millis(1));
+        let mut pn532 = PN532::new(WithTimeout::new(bus, Duration::from_millis(50)));
+        let begin = Instant::now();
+        assert_matches!(pn532.firmware_version().unwrap_err(), CommError::RecvError(RecvError::ReadError(WaitError::Timeout(_))));
+        assert!(begin.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,80 @@
+//! Wrappers of `PN532` which enforce calling `sam_configure` before working with tags.
+//!
+//! The ordering is enforced only when these wrappers are used, `PN532` created by `PN532::new`
+//! allows listing tags right away.
+
+use ::bus;
+use ::error::CommError;
+use super::{PN532, SAMMode};
+
+/// PN532 which wasn't configured yet.
+///
+/// Listing tags before SAM configuration usually fails, so this only allows configuring it.
+pub struct Unconfigured<D: bus::WaitRead + bus::BusWrite> {
+    pn532: PN532<D>,
+}
+
+impl<D: bus::WaitRead + bus::BusWrite> Unconfigured<D> {
+    /// Creates PN532 driver, this is the recommended entry point of the crate.
+    pub fn new(device: D) -> Self {
+        Unconfigured {
+            pn532: PN532::new(device),
+        }
+    }
+
+    /// Configures SAM and returns PN532 ready for working with tags.
+    ///
+    /// On failure, `self` is returned back inside the error, so configuration can be retried.
+    pub fn sam_configure(mut self, mode: SAMMode) -> Result<Configured<D>, ConfigureError<D>> {
+        match self.pn532.sam_configure(mode) {
+            Ok(()) => Ok(Configured { pn532: self.pn532 }),
+            Err(error) => Err(ConfigureError { pn532: self, error }),
+        }
+    }
+
+    pub fn into_inner(self) -> PN532<D> {
+        self.pn532
+    }
+}
+
+/// Failed `Unconfigured::sam_configure`.
+pub struct ConfigureError<D: bus::WaitRead + bus::BusWrite> {
+    /// The PN532, so that configuration can be retried.
+    pub pn532: Unconfigured<D>,
+    pub error: CommError<D::ReadError, D::WriteError>,
+}
+
+/// PN532 on which `sam_configure` succeeded.
+///
+/// Dereferences to `PN532`, so all its methods can be used.
+pub struct Configured<D: bus::WaitRead + bus::BusWrite> {
+    pn532: PN532<D>,
+}
+
+impl<D: bus::WaitRead + bus::BusWrite> Configured<D> {
+    /// Wraps PN532 which was configured by other means.
+    ///
+    /// This doesn't check anything, calling it on unconfigured PN532 only
+    /// brings back the confusing errors from listing tags.
+    pub fn assume_configured(pn532: PN532<D>) -> Self {
+        Configured { pn532 }
+    }
+
+    pub fn into_inner(self) -> PN532<D> {
+        self.pn532
+    }
+}
+
+impl<D: bus::WaitRead + bus::BusWrite> ::core::ops::Deref for Configured<D> {
+    type Target = PN532<D>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pn532
+    }
+}
+
+impl<D: bus::WaitRead + bus::BusWrite> ::core::ops::DerefMut for Configured<D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pn532
+    }
+}
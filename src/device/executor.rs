@@ -0,0 +1,313 @@
+//! Commands written once against `Executor`, independently of how they are delivered to PN532.
+//!
+//! The wrappers here only build the command and parse the reply, so the same logic
+//! can be reused by any implementation of `Executor`.
+
+use ::bus;
+use ::error::{CommResult, CommError, RecvError, DataError, SendError, Pn532Status};
+use super::{PN532, SAMMode, FirmwareVersion, GeneralStatus, TargetStatus, Baudrate};
+#[cfg(feature = "tags")]
+use super::check_list_reply;
+#[cfg(feature = "tags")]
+use super::tags_internal::{TagListOptions, TagBuffer};
+
+/// Executes single command: sends it to PN532 and receives the reply.
+pub trait Executor {
+    type ReadError: ::core::error::Error;
+    type WriteError: ::core::error::Error;
+
+    /// Sends `cmd` (starting with command code) and receives the reply.
+    ///
+    /// Checks that the response code matches the command and copies the data following it
+    /// into `out`, returning their length.
+    fn roundtrip(&mut self, cmd: &[u8], out: &mut [u8]) -> CommResult<usize, Self::ReadError, Self::WriteError>;
+}
+
+impl<D: bus::WaitRead + bus::BusWrite> Executor for PN532<D> {
+    type ReadError = D::ReadError;
+    type WriteError = D::WriteError;
+
+    fn roundtrip(&mut self, cmd: &[u8], out: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        self.command(cmd, out)
+    }
+}
+
+/// Reads version of the chip (command GetFirmwareVersion).
+pub fn firmware_version<E: Executor>(executor: &mut E) -> CommResult<FirmwareVersion, E::ReadError, E::WriteError> {
+    let mut buf = [0u8; 4];
+    let len = executor.roundtrip(&[0x02], &mut buf)?;
+    if len < buf.len() {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+
+    Ok(FirmwareVersion {
+        ic: buf[0],
+        ver: buf[1],
+        rev: buf[2],
+        support: buf[3],
+    })
+}
+
+/// Reads the general status of PN532 (command GetGeneralStatus).
+pub fn general_status<E: Executor>(executor: &mut E) -> CommResult<GeneralStatus, E::ReadError, E::WriteError> {
+    let invalid_baudrate = |b| CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(b, "baud rate code")));
+
+    let mut buf = [0u8; 12];
+    let len = executor.roundtrip(&[0x04], &mut buf)?;
+    if len < 3 {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+    let count = buf[2] as usize;
+    if count > 2 {
+        return Err(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(buf[2], "at most 2 targets"))));
+    }
+    if len < 4 + count * 4 {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+
+    let mut targets = [None; 2];
+    for (target, raw) in targets.iter_mut().zip(buf[3..(3 + count * 4)].chunks(4)) {
+        *target = Some(TargetStatus {
+            tag_number: raw[0],
+            rx_baudrate: Baudrate::from_code(raw[1]).ok_or_else(|| invalid_baudrate(raw[1]))?,
+            tx_baudrate: Baudrate::from_code(raw[2]).ok_or_else(|| invalid_baudrate(raw[2]))?,
+            modulation: raw[3],
+        });
+    }
+
+    Ok(GeneralStatus {
+        last_error: buf[0],
+        field_present: buf[1] != 0,
+        targets,
+        sam_status: buf[3 + count * 4],
+    })
+}
+
+/// Configures SAM (command SAMConfiguration).
+pub fn sam_configure<E: Executor>(executor: &mut E, mode: SAMMode) -> CommResult<(), E::ReadError, E::WriteError> {
+    let mut cmd_buf = [0x14, mode.code(), 0x01, 0x01];
+    let cmd = match mode.timeout() {
+        Some(to) => {
+            cmd_buf[2] = to.units();
+            &cmd_buf as &[u8]
+        }
+        None => {
+            &cmd_buf[0..3] as &[u8]
+        }
+    };
+
+    executor.roundtrip(cmd, &mut [])?;
+    Ok(())
+}
+
+/// Reads values of registers at given addresses into `values` (command ReadRegister).
+pub fn read_registers<E: Executor>(executor: &mut E, addresses: &[u16], values: &mut [u8]) -> CommResult<(), E::ReadError, E::WriteError> {
+    let mut cmd_buf = [0u8; 254];
+    if 1 + addresses.len() * 2 > cmd_buf.len() {
+        return Err(CommError::SendError(SendError::TooMuchData(1 + addresses.len() * 2)));
+    }
+
+    cmd_buf[0] = 0x06;
+    for (chunk, addr) in cmd_buf[1..].chunks_mut(2).zip(addresses) {
+        chunk[0] = (*addr >> 8) as u8;
+        chunk[1] = *addr as u8;
+    }
+
+    let len = executor.roundtrip(&cmd_buf[..(1 + addresses.len() * 2)], values)?;
+    if len < addresses.len() && len < values.len() {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+    Ok(())
+}
+
+/// Reads `count` registers at consecutive addresses starting with `start` into `out`.
+pub fn read_register_range<E: Executor>(executor: &mut E, start: u16, count: usize, out: &mut [u8]) -> CommResult<(), E::ReadError, E::WriteError> {
+    if out.len() < count {
+        return Err(CommError::RecvError(RecvError::Truncated { copied: 0, total: count }));
+    }
+
+    let mut addresses = [0u16; 126];
+    if count > addresses.len() {
+        return Err(CommError::SendError(SendError::TooMuchData(1 + count * 2)));
+    }
+    for (i, addr) in addresses[..count].iter_mut().enumerate() {
+        *addr = start.wrapping_add(i as u16);
+    }
+    read_registers(executor, &addresses[..count], &mut out[..count])
+}
+
+/// Writes values to registers, each item is pair of address and value (command WriteRegister).
+pub fn write_registers<E: Executor>(executor: &mut E, registers: &[(u16, u8)]) -> CommResult<(), E::ReadError, E::WriteError> {
+    let mut cmd_buf = [0u8; 254];
+    if 1 + registers.len() * 3 > cmd_buf.len() {
+        return Err(CommError::SendError(SendError::TooMuchData(1 + registers.len() * 3)));
+    }
+
+    cmd_buf[0] = 0x08;
+    for (chunk, &(addr, value)) in cmd_buf[1..].chunks_mut(3).zip(registers) {
+        chunk[0] = (addr >> 8) as u8;
+        chunk[1] = addr as u8;
+        chunk[2] = value;
+    }
+
+    executor.roundtrip(&cmd_buf[..(1 + registers.len() * 3)], &mut [])?;
+    Ok(())
+}
+
+/// Sends raw data to the tag and returns its response in `data_in` (command InCommunicateThru).
+pub fn communicate_thru<E: Executor>(executor: &mut E, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, E::ReadError, E::WriteError> {
+    use ::core::cmp::min;
+
+    let mut cmd_buf = [0u8; 254];
+    if 1 + data_out.len() > cmd_buf.len() {
+        return Err(CommError::SendError(SendError::TooMuchData(1 + data_out.len())));
+    }
+    cmd_buf[0] = 0x42;
+    cmd_buf[1..(1 + data_out.len())].copy_from_slice(data_out);
+
+    let mut buf = [0u8; 255];
+    let len = executor.roundtrip(&cmd_buf[..(1 + data_out.len())], &mut buf)?;
+    if len == 0 {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+    if let Some(status) = Pn532Status::from_status_byte(buf[0]) {
+        return Err(CommError::Status(status));
+    }
+
+    let to_copy = min(len - 1, data_in.len());
+    data_in[..to_copy].copy_from_slice(&buf[1..(1 + to_copy)]);
+    Ok(to_copy)
+}
+
+/// Changes baud rate of communication with the target (command InPSL).
+pub fn psl<E: Executor>(executor: &mut E, tag_number: u8, tx: Baudrate, rx: Baudrate) -> CommResult<(), E::ReadError, E::WriteError> {
+    let mut buf = [0u8; 1];
+    let len = executor.roundtrip(&[0x4E, tag_number, tx.code(), rx.code()], &mut buf)?;
+    if len == 0 {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+    match Pn532Status::from_status_byte(buf[0]) {
+        Some(status) => Err(CommError::Status(status)),
+        None => Ok(()),
+    }
+}
+
+/// Sets configuration `item` to `data` (command RFConfiguration), `data` can be at most 11 bytes long.
+pub fn rf_configuration<E: Executor>(executor: &mut E, item: u8, data: &[u8]) -> CommResult<(), E::ReadError, E::WriteError> {
+    let mut cmd_buf = [0u8; 13];
+    if 2 + data.len() > cmd_buf.len() {
+        return Err(CommError::SendError(SendError::TooMuchData(2 + data.len())));
+    }
+    cmd_buf[0] = 0x32;
+    cmd_buf[1] = item;
+    cmd_buf[2..(2 + data.len())].copy_from_slice(data);
+
+    executor.roundtrip(&cmd_buf[..(2 + data.len())], &mut [])?;
+    Ok(())
+}
+
+/// Switches the RF field off and on again (RFConfiguration item 0x01).
+pub fn reset_rf_field<E: Executor>(executor: &mut E) -> CommResult<(), E::ReadError, E::WriteError> {
+    rf_configuration(executor, 0x01, &[0x00])?;
+    rf_configuration(executor, 0x01, &[0x01])
+}
+
+/// Runs one of the self tests of PN532 (command Diagnose).
+pub fn diagnose<E: Executor>(executor: &mut E, test: u8, params: &[u8], result: &mut [u8]) -> CommResult<usize, E::ReadError, E::WriteError> {
+    let mut cmd_buf = [0u8; 254];
+    if 2 + params.len() > cmd_buf.len() {
+        return Err(CommError::SendError(SendError::TooMuchData(2 + params.len())));
+    }
+    cmd_buf[0] = 0x00;
+    cmd_buf[1] = test;
+    cmd_buf[2..(2 + params.len())].copy_from_slice(params);
+
+    executor.roundtrip(&cmd_buf[..(2 + params.len())], result)
+}
+
+/// Lists tags according to `options` and stores the raw reply, including response code, in `buf`.
+#[cfg(feature = "tags")]
+pub fn list_tags<'buf, E: Executor, O: TagListOptions<'buf>>(executor: &mut E, options: &O, buf: &mut TagBuffer) -> CommResult<(), E::ReadError, E::WriteError> {
+    buf.set_len(0);
+    if options.wake_up() {
+        reset_rf_field(executor)?;
+    }
+
+    let mut cmd_buf = [0u8; 254];
+    cmd_buf[0] = options.command_code();
+    let len = options.fill_buf(&mut cmd_buf[1..]);
+
+    let reply_len = {
+        let raw_buf = buf.raw_mut();
+        let len = executor.roundtrip(&cmd_buf[..(1 + len)], &mut raw_buf[1..])?;
+        raw_buf[0] = cmd_buf[0] + 1;
+        check_list_reply(&raw_buf[..(1 + len)], cmd_buf[0])?;
+        1 + len
+    };
+    buf.set_len(reply_len);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Executor;
+    use ::error::{CommResult, CommError, RecvError};
+    use ::std::io;
+
+    // Replies to every command with the same data.
+    struct Canned(&'static [u8]);
+
+    impl Executor for Canned {
+        type ReadError = io::Error;
+        type WriteError = io::Error;
+
+        fn roundtrip(&mut self, _: &[u8], out: &mut [u8]) -> CommResult<usize, io::Error, io::Error> {
+            let len = ::std::cmp::min(self.0.len(), out.len());
+            out[..len].copy_from_slice(&self.0[..len]);
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn firmware_version() {
+        let version = super::firmware_version(&mut Canned(&[0x32, 0x01, 0x06, 0x07])).unwrap();
+        assert_eq!((version.ic, version.ver, version.rev, version.support), (0x32, 0x01, 0x06, 0x07));
+        assert_matches!(super::firmware_version(&mut Canned(&[0x32])).unwrap_err(), CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn read_register_range() {
+        let mut out = [0u8; 3];
+        super::read_register_range(&mut Canned(&[0x84, 0x85, 0x4D]), 0x6318, 3, &mut out).unwrap();
+        assert_eq!(out, [0x84, 0x85, 0x4D]);
+        assert_matches!(super::read_register_range(&mut Canned(&[0x84]), 0x6318, 3, &mut out).unwrap_err(), CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn list_tags() {
+        use ::device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        let mut buf = TagBuffer::new();
+        super::list_tags(&mut Canned(&[0x00]), &options, &mut buf).unwrap();
+        assert_eq!(buf.raw_mut()[..2], [0x4B, 0x00]);
+        assert_matches!(super::list_tags(&mut Canned(&[0x03]), &options, &mut buf).unwrap_err(), CommError::RecvError(RecvError::InvalidData(_)));
+    }
+
+    #[test]
+    fn diagnose_too_long() {
+        use ::error::SendError;
+
+        assert_eq!(super::diagnose(&mut Canned(&[0x00]), 0x00, &[0x55; 252], &mut [0u8; 1]).unwrap(), 1);
+        assert_matches!(super::diagnose(&mut Canned(&[0x00]), 0x00, &[0x55; 253], &mut [0u8; 1]).unwrap_err(), CommError::SendError(SendError::TooMuchData(255)));
+    }
+
+    #[test]
+    fn rf_configuration_too_long() {
+        use ::error::SendError;
+
+        super::rf_configuration(&mut Canned(&[]), 0x0B, &[0x00; 11]).unwrap();
+        assert_matches!(super::rf_configuration(&mut Canned(&[]), 0x0B, &[0x00; 12]).unwrap_err(), CommError::SendError(SendError::TooMuchData(14)));
+    }
+}
@@ -1,20 +1,111 @@
 mod proto;
+#[cfg(feature = "tags")]
 pub mod tags_internal;
+pub mod configured;
+pub mod registers;
+pub mod executor;
+#[cfg(all(feature = "std", feature = "tags"))]
+pub mod sync;
 
 use ::bus;
-use self::proto::PN532Proto;
-use ::error::{CommResult, CommError, RecvError, DataError};
-use device::tags_internal::{TagListOptions, TagBuffer, Tags};
+use self::proto::{PN532Proto, DATA_OFFSET};
+pub use self::proto::{Frame, ResponseHeader, ProtoStats, Direction, MAX_PREAMBLE_LEN, FRAME_BUF_LEN, DEFAULT_MAX_SCAN_LEN};
+#[cfg(feature = "std")]
+pub use self::proto::TraceFn;
+use ::error::{CommResult, CommError, RecvError, DataError, SendError, Pn532Status, UnsupportedEmulatedUid, InvalidTargetNumber, WaitResult};
+#[cfg(feature = "tags")]
+use ::error::WaitError;
+#[cfg(feature = "tags")]
+use device::tags_internal::{TagListOptions, TagBuffer, Tags, MifareKeyType, ISO14443A, ISO14443AListOptions};
+#[cfg(all(feature = "std", feature = "tags"))]
+use device::tags_internal::DetectedCard;
+use self::registers::ciu;
 
+
+/// Timeout used by SAM configuration, in units of 50 ms.
+///
+/// The range is 50 ms to 12.75 s, zero isn't allowed since virtual card mode would be unusable with it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SamTimeout(::core::num::NonZeroU8);
+
+impl SamTimeout {
+    /// The longest possible timeout, 12.75 s.
+    pub const MAX: SamTimeout = SamTimeout(::core::num::NonZeroU8::MAX);
+
+    /// Creates timeout of `units` * 50 ms, returns `None` if `units` is zero.
+    pub fn new(units: u8) -> Option<Self> {
+        ::core::num::NonZeroU8::new(units).map(SamTimeout)
+    }
+
+    /// Creates the shortest timeout which is at least `millis` long.
+    ///
+    /// Returns `None` if `millis` is zero or longer than 12.75 s.
+    pub fn from_millis(millis: u32) -> Option<Self> {
+        if millis > 12750 {
+            return None;
+        }
+        Self::new(millis.div_ceil(50) as u8)
+    }
+
+    /// Number of 50 ms units.
+    pub fn units(self) -> u8 {
+        self.0.get()
+    }
+
+    pub fn as_millis(self) -> u32 {
+        u32::from(self.units()) * 50
+    }
+}
+
+/// Mode of Security Access Module.
+///
+/// The timeout is only used in virtual card mode, but if it's given in other modes,
+/// it's sent to PN532 together with IRQ byte.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SAMMode {
-    Normal(Option<u8>),
-    VirtualCard(u8),
-    WiredCard(Option<u8>),
-    DualCard(Option<u8>),
+    Normal(Option<SamTimeout>),
+    VirtualCard(SamTimeout),
+    WiredCard(Option<SamTimeout>),
+    DualCard(Option<SamTimeout>),
 }
 
 impl SAMMode {
+    /// SAM isn't used, the usual mode when PN532 works as a plain reader.
+    pub fn normal() -> Self {
+        SAMMode::Normal(None)
+    }
+
+    /// PN532 and SAM act as a single contactless card, `timeout` bounds the transaction.
+    pub fn virtual_card(timeout: SamTimeout) -> Self {
+        SAMMode::VirtualCard(timeout)
+    }
+
+    /// The host communicates with SAM as a contact card.
+    pub fn wired_card() -> Self {
+        SAMMode::WiredCard(None)
+    }
+
+    /// Both PN532 and SAM are visible as separate contactless cards.
+    pub fn dual_card() -> Self {
+        SAMMode::DualCard(None)
+    }
+
+    /// Replaces the timeout sent to PN532.
+    ///
+    /// Only virtual card mode uses it, other modes just send it along with IRQ byte.
+    pub fn with_timeout(self, timeout: SamTimeout) -> Self {
+        use self::SAMMode::*;
+
+        match self {
+            Normal(_) => Normal(Some(timeout)),
+            VirtualCard(_) => VirtualCard(timeout),
+            WiredCard(_) => WiredCard(Some(timeout)),
+            DualCard(_) => DualCard(Some(timeout)),
+        }
+    }
+
     fn code(self) -> u8 {
         use self::SAMMode::*;
 
@@ -26,7 +117,7 @@ impl SAMMode {
         }
     }
 
-    fn timeout(self) -> Option<u8> {
+    fn timeout(self) -> Option<SamTimeout> {
         use self::SAMMode::*;
 
         match self {
@@ -38,78 +129,2583 @@ impl SAMMode {
     }
 }
 
+/// Threshold of high current detection for antenna self test.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HighCurrentThreshold {
+    Ma45,
+    Ma60,
+    Ma75,
+    Ma90,
+}
+
+/// Threshold of low current detection for antenna self test.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LowCurrentThreshold {
+    Ma25,
+    Ma35,
+    Ma45,
+    Ma55,
+}
+
+/// Thresholds used by antenna self test.
+///
+/// Antenna is considered faulty if the current drawn by it is out of the range.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AntennaThresholds {
+    pub high_current: HighCurrentThreshold,
+    pub low_current: LowCurrentThreshold,
+}
+
+impl AntennaThresholds {
+    // Bits 5-4: high current threshold
+    // Bits 2-1: low current threshold
+    // Bit 0: enable antenna detector (always set)
+    fn code(self) -> u8 {
+        let high = match self.high_current {
+            HighCurrentThreshold::Ma45 => 0x00,
+            HighCurrentThreshold::Ma60 => 0x10,
+            HighCurrentThreshold::Ma75 => 0x20,
+            HighCurrentThreshold::Ma90 => 0x30,
+        };
+        let low = match self.low_current {
+            LowCurrentThreshold::Ma25 => 0x00,
+            LowCurrentThreshold::Ma35 => 0x02,
+            LowCurrentThreshold::Ma45 => 0x04,
+            LowCurrentThreshold::Ma55 => 0x06,
+        };
+        high | low | 0x01
+    }
+}
+
+/// Result of antenna self test.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AntennaHealth {
+    /// Raw result byte returned by PN532.
+    pub result: u8,
+}
+
+impl AntennaHealth {
+    /// Returns true if the antenna passed the test.
+    pub fn is_ok(&self) -> bool {
+        self.result == 0x00
+    }
+}
+
+/// Sensitivity of the receiver, set by `PN532::set_rx_sensitivity`.
+///
+/// Higher sensitivity helps with marginal antennas and distant tags, but the receiver
+/// also starts decoding noise, which shows up as CRC, parity or collision errors.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RxSensitivity {
+    Low,
+    /// Value used by PN532 for ISO14443A at 106 kbps.
+    Normal,
+    High,
+    Maximum,
+}
+
+impl RxSensitivity {
+    // Bits 7-4: MinLevel, the weakest signal the decoder accepts
+    // Bits 2-0: CollLevel, the weakest half-bit considered a collision
+    fn rx_threshold(self) -> u8 {
+        match self {
+            RxSensitivity::Low => 0xB5,
+            RxSensitivity::Normal => 0x85,
+            RxSensitivity::High => 0x55,
+            RxSensitivity::Maximum => 0x35,
+        }
+    }
+}
+
+/// Error flags of the last RF communication, read from CIU_Error register (0x6336).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RfErrorFlags {
+    /// Raw value of the register.
+    pub bits: u8,
+}
+
+impl RfErrorFlags {
+    /// Returns true if no error flag is set.
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Bit 0 (ProtocolErr): SOF is incorrect or the data didn't match the protocol.
+    pub fn protocol(&self) -> bool {
+        self.bits & 0x01 != 0
+    }
+
+    /// Bit 1 (ParityErr): parity check failed.
+    pub fn parity(&self) -> bool {
+        self.bits & 0x02 != 0
+    }
+
+    /// Bit 2 (CRCErr): CRC of received data is wrong.
+    pub fn crc(&self) -> bool {
+        self.bits & 0x04 != 0
+    }
+
+    /// Bit 3 (CollErr): bit collision was detected.
+    pub fn collision(&self) -> bool {
+        self.bits & 0x08 != 0
+    }
+
+    /// Bit 4 (BufferOvfl): data were written to full FIFO.
+    pub fn buffer_overflow(&self) -> bool {
+        self.bits & 0x10 != 0
+    }
+
+    /// Bit 5 (RFErr): the counterpart didn't switch RF field on in time (active mode).
+    pub fn rf(&self) -> bool {
+        self.bits & 0x20 != 0
+    }
+
+    /// Bit 6 (TempErr): antenna drivers were switched off because of overheating.
+    pub fn temperature(&self) -> bool {
+        self.bits & 0x40 != 0
+    }
+
+    /// Bit 7 (WrErr): data were written to FIFO at wrong time.
+    pub fn write(&self) -> bool {
+        self.bits & 0x80 != 0
+    }
+}
+
+/// Raw status registers of CIU, see `PN532::health`.
+///
+/// PN532 has no readable temperature or supply voltage. Its temperature sensor only
+/// switches the antenna drivers off when the chip overheats (about 125 °C) and reports it
+/// by TempErr flag, which is the only thermal information available.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ModuleHealth {
+    /// CIU_Error (0x6336), error flags of the last RF communication.
+    pub rf_errors: RfErrorFlags,
+    /// CIU_Status1 (0x6337), state of FIFO, timer and CRC coprocessor.
+    pub status_1: u8,
+    /// CIU_Status2 (0x6338), modem state and whether MIFARE Crypto1 is on.
+    pub status_2: u8,
+    /// CIU_TxControl (0x6304), which includes enabling of antenna drivers.
+    pub tx_control: u8,
+}
+
+impl ModuleHealth {
+    /// Returns true if the antenna drivers were switched off because of overheating.
+    pub fn overheated(&self) -> bool {
+        self.rf_errors.temperature()
+    }
+
+    /// Returns true if at least one antenna driver generates RF field.
+    pub fn field_on(&self) -> bool {
+        self.tx_control & 0x03 != 0
+    }
+}
+
+/// Kind of the chip, as reported in firmware version.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IcKind {
+    PN532,
+    PN533,
+    Other(u8),
+}
+
+/// Version of the chip and its firmware.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FirmwareVersion {
+    /// Identifies the chip (0x32 for PN532).
+    pub ic: u8,
+    pub ver: u8,
+    pub rev: u8,
+    /// Flags of supported protocols (bit 0: ISO14443A, bit 1: ISO14443B, bit 2: ISO18092).
+    pub support: u8,
+}
+
+impl FirmwareVersion {
+    pub fn ic_kind(&self) -> IcKind {
+        match self.ic {
+            0x32 => IcKind::PN532,
+            0x33 => IcKind::PN533,
+            other => IcKind::Other(other),
+        }
+    }
+
+    /// Returns false if the device is some other chip, which might use slightly different framing.
+    pub fn is_pn532(&self) -> bool {
+        self.ic_kind() == IcKind::PN532
+    }
+
+    /// Returns known differences of this chip from PN532 which the crate works around.
+    ///
+    /// Quirks are looked up by the chip and its firmware version:
+    ///
+    /// | IC    | Version | Quirks                   |
+    /// |-------|---------|--------------------------|
+    /// | 0x32  | any     | none                     |
+    /// | 0x33  | any     | `no_sam`, `no_auto_poll` |
+    /// | other | any     | `no_sam`, `no_auto_poll` |
+    ///
+    /// No differences between firmware versions of the same chip are known so far.
+    pub fn quirks(&self) -> Quirks {
+        const PN53X_ONLY: Quirks = Quirks { no_sam: true, no_auto_poll: true };
+
+        match (self.ic_kind(), self.ver, self.rev) {
+            (IcKind::PN532, _, _) => Quirks::default(),
+            // PN533 is USB only, its SAM pins are used by the USB interface.
+            (IcKind::PN533, _, _) => PN53X_ONLY,
+            // Unknown chips are assumed to implement only the common PN53x commands.
+            (IcKind::Other(_), _, _) => PN53X_ONLY,
+        }
+    }
+}
+
+/// Differences between chips (and their firmware) compatible with PN532, which `PN532` adjusts to.
+///
+/// They are known only after calling `PN532::firmware_version`, until then PN532 without
+/// any quirks is assumed.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Quirks {
+    /// The chip has no SAM interface and rejects SAMConfiguration (PN533 and others).
+    ///
+    /// Such chip always works as if SAM was in normal mode, so `sam_configure` only records
+    /// `SAMMode::Normal` without sending it. Other modes are still sent and the chip rejects them.
+    pub no_sam: bool,
+    /// The chip doesn't implement InAutoPoll, which is specific to PN532.
+    ///
+    /// `poll_once` lists ISO14443A tags using InListPassiveTarget instead,
+    /// so cards of other types aren't found on such chip.
+    pub no_auto_poll: bool,
+}
+
+/// NFCID3 identifying initiator or target of NFC-DEP (ISO18092) communication.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Nfcid3(pub [u8; 10]);
+
+impl Nfcid3 {
+    /// Creates NFCID3 from slice, returns `None` if it isn't exactly 10 bytes long.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 10 {
+            return None;
+        }
+        let mut id = [0u8; 10];
+        id.copy_from_slice(bytes);
+        Some(Nfcid3(id))
+    }
+
+    /// Generates random NFCID3, as ISO18092 recommends for each activation.
+    ///
+    /// Uses the randomly seeded hasher of std, which is good enough for identifiers
+    /// but not for anything security-related.
+    #[cfg(feature = "std")]
+    pub fn random() -> Self {
+        use ::std::collections::hash_map::RandomState;
+        use ::std::hash::BuildHasher;
+
+        let state = RandomState::new();
+        let first = state.hash_one(0u8).to_le_bytes();
+        let second = state.hash_one(1u8).to_le_bytes();
+        let mut id = [0u8; 10];
+        id[..8].copy_from_slice(&first);
+        id[8..].copy_from_slice(&second[..2]);
+        Nfcid3(id)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 10] {
+        &self.0
+    }
+}
+
+/// MIFARE parameters of TgInitAsTarget, which determine how PN532 answers anticollision
+/// when emulating ISO14443A card.
+///
+/// PN532 supports only single cascade level in target mode, so cards with 7 or 10 byte UID
+/// can't be emulated. The first byte of the UID is always 0x08 (random UID), only
+/// the remaining three can be chosen.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MifareParams {
+    pub sens_res: u16,
+    nfcid1: [u8; 4],
+    pub sel_res: u8,
+}
+
+impl MifareParams {
+    /// Creates the parameters, `nfcid1` must be 4 bytes long and start with 0x08.
+    pub fn new(sens_res: u16, nfcid1: &[u8], sel_res: u8) -> Result<Self, UnsupportedEmulatedUid> {
+        if nfcid1.len() != 4 {
+            return Err(UnsupportedEmulatedUid::Length(nfcid1.len()));
+        }
+        if nfcid1[0] != 0x08 {
+            return Err(UnsupportedEmulatedUid::FirstByte(nfcid1[0]));
+        }
+        let mut id = [0u8; 4];
+        id.copy_from_slice(nfcid1);
+        Ok(MifareParams { sens_res, nfcid1: id, sel_res })
+    }
+
+    pub fn nfcid1(&self) -> &[u8; 4] {
+        &self.nfcid1
+    }
+
+    /// Encodes the parameters as they follow the mode byte in TgInitAsTarget command.
+    pub fn to_bytes(&self) -> [u8; 6] {
+        // Unlike in InListPassiveTarget reply, SENS_RES is sent LSB first here.
+        let sens_res = self.sens_res.to_le_bytes();
+        [sens_res[0], sens_res[1], self.nfcid1[1], self.nfcid1[2], self.nfcid1[3], self.sel_res]
+    }
+}
+
+/// Block number of ISO-DEP protocol handled on the host side, see `PN532::iso_dep_exchange_thru`.
+///
+/// Create new state after each activation of the tag.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct IsoDepState {
+    block_number: u8,
+}
+
+impl IsoDepState {
+    pub fn new() -> Self {
+        IsoDepState::default()
+    }
+}
+
+/// Baud rate of communication with a target.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Baudrate {
+    Br106,
+    Br212,
+    Br424,
+}
+
+impl Baudrate {
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x00 => Some(Baudrate::Br106),
+            0x01 => Some(Baudrate::Br212),
+            0x02 => Some(Baudrate::Br424),
+            _ => None,
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Baudrate::Br106 => 0x00,
+            Baudrate::Br212 => 0x01,
+            Baudrate::Br424 => 0x02,
+        }
+    }
+
+    pub fn kbps(self) -> u32 {
+        match self {
+            Baudrate::Br106 => 106,
+            Baudrate::Br212 => 212,
+            Baudrate::Br424 => 424,
+        }
+    }
+}
+
+/// Status of a target currently handled by PN532.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TargetStatus {
+    /// Logical number of the target, as used by `transceive`.
+    pub tag_number: u8,
+    /// Baud rate of reception from the target.
+    pub rx_baudrate: Baudrate,
+    /// Baud rate of transmission to the target.
+    pub tx_baudrate: Baudrate,
+    /// Raw modulation type code.
+    pub modulation: u8,
+}
+
+/// Result of GetGeneralStatus command.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeneralStatus {
+    /// Error code of the last command, zero if there was none.
+    pub last_error: u8,
+    /// True if external RF field was detected.
+    pub field_present: bool,
+    targets: [Option<TargetStatus>; 2],
+    /// Raw status of SAM, see `sam` for its interpretation.
+    pub sam_status: u8,
+}
+
+impl GeneralStatus {
+    /// Returns the targets PN532 currently handles (at most two).
+    pub fn targets(&self) -> impl Iterator<Item=&TargetStatus> {
+        self.targets.iter().flatten()
+    }
+
+    /// Interprets the status of SAM.
+    pub fn sam(&self) -> SamStatus {
+        SamStatus::from_code(self.sam_status)
+    }
+}
+
+/// Status of SAM reported by GetGeneralStatus.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SamStatus {
+    /// SAM didn't signal any event.
+    Normal,
+    /// Negative pulse was detected on the CLAD line, SAM signaled a fault.
+    NegativePulse,
+    /// Value not documented in the user manual.
+    Other(u8),
+}
+
+impl SamStatus {
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x00 => SamStatus::Normal,
+            0x80 => SamStatus::NegativePulse,
+            other => SamStatus::Other(other),
+        }
+    }
+
+    /// Returns true if SAM signaled a fault.
+    pub fn is_fault(self) -> bool {
+        self != SamStatus::Normal
+    }
+}
+
 pub struct PN532<D: bus::WaitRead + bus::BusWrite> {
     device: PN532Proto<D>,
+    sam_mode: Option<SAMMode>,
+    firmware: Option<FirmwareVersion>,
+    current_target: u8,
+    // Last values set by `set_max_retries`, PN532 can't report them.
+    max_retries: [u8; 3],
+}
+
+#[cfg(feature = "std")]
+impl<D: bus::BusRead + bus::BusWrite> PN532<bus::BusyWait<D>> {
+    /// Wraps raw bus into `BusyWait` with default delay, which is the common way to wait for PN532.
+    ///
+    /// Use `new` with other wrapper (e.g. `I2CBus`) for different waiting strategy.
+    pub fn with_busy_wait(device: D) -> Self {
+        PN532::new(bus::BusyWait::new(device))
+    }
 }
 
 impl<D: bus::WaitRead + bus::BusWrite> PN532<D> {
+    /// Creates PN532 driver without checking that SAM gets configured before working with tags.
+    ///
+    /// Prefer `Unconfigured::new`, this is an escape hatch for callers managing the ordering themselves.
     pub fn new(device: D) -> Self {
         PN532 {
-            device: PN532Proto::new(device)
+            device: PN532Proto::new(device),
+            sam_mode: None,
+            firmware: None,
+            current_target: 1,
+            max_retries: [0xFF, 0x01, 0xFF],
         }
     }
 
+    /// Configures SAM (Security Access Module), which must be done before working with tags.
+    ///
+    /// On chips without SAM, if they were detected by `firmware_version`, normal mode
+    /// is only recorded, see `Quirks::no_sam`.
     pub fn sam_configure(&mut self, mode: SAMMode) -> CommResult<(), D::ReadError, D::WriteError> {
-        let mut cmd_buf = [0x14, mode.code(), 0x01, 0x01];
-        let cmd = match mode.timeout() {
-            Some(to) => {
-                cmd_buf[2] = to;
-                &cmd_buf as &[u8]
-            }
-            None => {
-                &cmd_buf[0..3] as &[u8]
+        if let (true, SAMMode::Normal(_)) = (self.quirks().no_sam, mode) {
+            self.sam_mode = Some(mode);
+            return Ok(());
+        }
+
+        executor::sam_configure(self, mode)?;
+        self.sam_mode = Some(mode);
+        Ok(())
+    }
+
+    /// Returns the mode last successfully set by `sam_configure`.
+    ///
+    /// PN532 can't report its SAM mode, so this is only cached on the host side
+    /// and `None` means it wasn't configured through this instance yet.
+    pub fn sam_mode(&self) -> Option<SAMMode> {
+        self.sam_mode
+    }
+
+    pub fn firmware_version(&mut self) -> CommResult<FirmwareVersion, D::ReadError, D::WriteError> {
+        let version = executor::firmware_version(self)?;
+        self.firmware = Some(version);
+        Ok(version)
+    }
+
+    /// Returns quirks of the chip detected by the last successful `firmware_version`.
+    pub fn quirks(&self) -> Quirks {
+        self.firmware.map_or(Quirks::default(), |version| version.quirks())
+    }
+
+    /// Reads the general status of PN532 (command GetGeneralStatus).
+    pub fn general_status(&mut self) -> CommResult<GeneralStatus, D::ReadError, D::WriteError> {
+        executor::general_status(self)
+    }
+
+    /// Reads values of registers at given addresses into `values`.
+    ///
+    /// Addresses of CIU registers are in the range 0x6301 - 0x633F.
+    pub fn read_registers(&mut self, addresses: &[u16], values: &mut [u8]) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::read_registers(self, addresses, values)
+    }
+
+    /// Reads `count` registers at consecutive addresses starting with `start` into `out`.
+    ///
+    /// Up to 126 registers can be read at once. If `out` is shorter than `count`,
+    /// `RecvError::Truncated` is returned without communicating with PN532.
+    pub fn read_register_range(&mut self, start: u16, count: usize, out: &mut [u8]) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::read_register_range(self, start, count, out)
+    }
+
+    /// Writes values to registers. Each item is pair of address and value.
+    pub fn write_registers(&mut self, registers: &[(u16, u8)]) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::write_registers(self, registers)
+    }
+
+    /// Sends raw data to the tag and returns its response in `data_in`.
+    ///
+    /// Unlike `transceive`, PN532 doesn't handle the protocol of the tag, so
+    /// the data are sent as they are (except CRC, see `set_crc`).
+    pub fn communicate_thru(&mut self, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        executor::communicate_thru(self, data_out, data_in)
+    }
+
+    /// Exchanges APDU with ISO14443-4 (ISO-DEP) tag using `communicate_thru`, handling
+    /// the block protocol on the host side.
+    ///
+    /// `transceive` should be preferred, since PN532 handles the protocol itself there,
+    /// including waiting time extensions. This is for tags activated by other means
+    /// than InListPassiveTarget, where PN532 doesn't know about the protocol.
+    /// Requests for waiting time extension (S(WTX) blocks) are acknowledged automatically
+    /// and chained responses are collected into `data_in`. CRC must be enabled (see `set_crc`).
+    /// If `data_in` is too small, `RecvError::Truncated` is returned after the chain is read.
+    pub fn iso_dep_exchange_thru(&mut self, state: &mut IsoDepState, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        use ::core::cmp::min;
+
+        const CHAINING: u8 = 0x10;
+
+        let mut block = [0u8; 253];
+        if 1 + data_out.len() > block.len() {
+            return Err(CommError::SendError(SendError::TooMuchData(1 + data_out.len())));
+        }
+        block[0] = 0x02 | state.block_number;
+        block[1..(1 + data_out.len())].copy_from_slice(data_out);
+        let mut block_len = 1 + data_out.len();
+
+        let mut received = 0;
+        let mut response = [0u8; 255];
+        loop {
+            let len = self.communicate_thru(&block[..block_len], &mut response)?;
+            if len == 0 {
+                return Err(CommError::RecvError(RecvError::UnexpectedEnd));
             }
-        };
+            let pcb = response[0];
 
-        try!(self.device.send_wait_ack(cmd));
-        let mut rcvbuf = [0u8];
-        let len = try!(self.device.recv_reply_ack(&mut rcvbuf));
-        if len > 0 {
-            if rcvbuf[0] == 0x15 {
-                Ok(())
+            if pcb & 0xE2 == 0x02 {
+                // I-block, receiving it completes the exchange of our block.
+                state.block_number ^= 1;
+                if received < data_in.len() {
+                    let to_copy = min(len - 1, data_in.len() - received);
+                    data_in[received..(received + to_copy)].copy_from_slice(&response[1..(1 + to_copy)]);
+                }
+                received += len - 1;
+                if pcb & CHAINING == 0 {
+                    if received > data_in.len() {
+                        return Err(CommError::RecvError(RecvError::Truncated { copied: data_in.len(), total: received }));
+                    }
+                    return Ok(received);
+                }
+                // R(ACK) requests the next part of the chain.
+                block[0] = 0xA2 | state.block_number;
+                block_len = 1;
+            } else if pcb & 0xF7 == 0xF2 {
+                // S(WTX) request, answered with the same multiplier.
+                if len < 2 {
+                    return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+                }
+                block[0] = 0xF2;
+                block[1] = response[1] & 0x3F;
+                block_len = 2;
             } else {
-                Err(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(rcvbuf[0], "0x15"))))
+                return Err(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(pcb, "I-block or S(WTX)"))));
             }
-        } else {
-            Err(CommError::RecvError(RecvError::UnexpectedEnd))
         }
     }
 
-    pub fn list_tags<'buf, 's, O: TagListOptions<'buf>>(&'s mut self, options: O, buf: &'buf mut TagBuffer) -> CommResult<Tags<'s, 'buf, O::Response, Self>, D::ReadError, D::WriteError> {
-        unsafe {
-            let raw_buf = ::core::intrinsics::transmute::<&mut TagBuffer, &mut [u8; 256]>(buf);
-            raw_buf[0] = 0x4A;
-            let len = options.fill_buf(&mut raw_buf[1..]);
+    /// Changes baud rate of communication with the target (command InPSL).
+    ///
+    /// `tx` is used from PN532 to the target, `rx` in the opposite direction. PN532 sends PSL_REQ
+    /// to NFC-DEP targets or PPS to ISO14443-4 tags and switches the rate itself, so following
+    /// exchanges use the new rate. Status error means the target rejected the change.
+    pub fn psl(&mut self, tag_number: u8, tx: Baudrate, rx: Baudrate) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::psl(self, tag_number, tx, rx)
+    }
 
-            try!(self.device.send_wait_ack(&raw_buf[..(1 + len)]));
-            try!(self.device.recv_reply_ack(raw_buf as &mut [u8]));
+    /// Sends ISO14443-3 HLTA to the last activated type A tag.
+    ///
+    /// HLTA has inverted success semantics: a halted tag doesn't respond at all, so timeout
+    /// means success and any response means the tag didn't accept the command.
+    /// The tag won't respond to REQA until it leaves the field, only to WUPA.
+    pub fn halt_iso14443a(&mut self) -> CommResult<(), D::ReadError, D::WriteError> {
+        let mut buf = [0u8; 1];
+        match self.communicate_thru(&[0x50, 0x00], &mut buf) {
+            Err(CommError::Status(Pn532Status::Timeout)) => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Err(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(buf[0], "no response to HLTA")))),
         }
+    }
 
-        unsafe {
-            Ok(Tags::new(buf, self))
+    /// Enables or disables generating CRC of transmitted data and checking CRC of received data.
+    ///
+    /// This modifies bit 7 of CIU_TxMode (0x6302) and CIU_RxMode (0x6303) registers,
+    /// keeping other bits intact. Both registers are 0x00 after reset,
+    /// but PN532 enables CRC itself when activating ISO14443A tag.
+    /// The setting affects `communicate_thru`.
+    pub fn set_crc(&mut self, tx: bool, rx: bool) -> CommResult<(), D::ReadError, D::WriteError> {
+        const CRC_EN: u8 = 0x80;
+
+        let mut modes = [0u8; 2];
+        self.read_registers(&[ciu::TX_MODE, ciu::RX_MODE], &mut modes)?;
+        let tx_mode = if tx { modes[0] | CRC_EN } else { modes[0] & !CRC_EN };
+        let rx_mode = if rx { modes[1] | CRC_EN } else { modes[1] & !CRC_EN };
+        self.write_registers(&[(ciu::TX_MODE, tx_mode), (ciu::RX_MODE, rx_mode)])
+    }
+
+    /// Reads error flags of the last RF communication from CIU_Error register (0x6336).
+    ///
+    /// The flags are more detailed than the status byte of InDataExchange,
+    /// which is useful after `communicate_thru` or `transceive_bits`.
+    pub fn read_rf_error(&mut self) -> CommResult<RfErrorFlags, D::ReadError, D::WriteError> {
+        let mut error = [0u8];
+        self.read_registers(&[ciu::ERROR], &mut error)?;
+        Ok(RfErrorFlags { bits: error[0] })
+    }
+
+    /// Clears error flags of the last RF communication.
+    ///
+    /// CIU_Error register is read-only, the flags are cleared when CIU starts a command,
+    /// so this starts Idle command (CIU_Command, 0x6331) and flushes FIFO (bit 7 of
+    /// CIU_FIFOLevel, 0x633A), which clears BufferOvfl. TempErr stays set while
+    /// the antenna drivers are too hot.
+    pub fn clear_rf_error(&mut self) -> CommResult<(), D::ReadError, D::WriteError> {
+        const IDLE: u8 = 0x00;
+        const FLUSH_BUFFER: u8 = 0x80;
+
+        self.write_registers(&[(ciu::COMMAND, IDLE), (ciu::FIFO_LEVEL, FLUSH_BUFFER)])
+    }
+
+    /// Enables or disables generating and checking parity bits.
+    ///
+    /// This modifies bit 4 (ParityDisable) of CIU_ManualRCV (0x630D) register.
+    /// The setting affects `communicate_thru` and `transceive_bits`.
+    pub fn set_parity(&mut self, enabled: bool) -> CommResult<(), D::ReadError, D::WriteError> {
+        const PARITY_DISABLE: u8 = 0x10;
+
+        let mut manual_rcv = [0u8];
+        self.read_registers(&[ciu::MANUAL_RCV], &mut manual_rcv)?;
+        let manual_rcv = if enabled { manual_rcv[0] & !PARITY_DISABLE } else { manual_rcv[0] | PARITY_DISABLE };
+        self.write_registers(&[(ciu::MANUAL_RCV, manual_rcv)])
+    }
+
+    /// Sets sensitivity of the receiver by writing CIU_RxThreshold (0x6308) register.
+    ///
+    /// Raising the sensitivity may help to read tags with weak antenna, but setting it
+    /// too high makes the receiver pick up noise, causing spurious RF errors.
+    /// PN532 loads its analog settings (including this register) when listing tags,
+    /// so the value should be set again after `list_tags`.
+    pub fn set_rx_sensitivity(&mut self, level: RxSensitivity) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.write_registers(&[(ciu::RX_THRESHOLD, level.rx_threshold())])
+    }
+
+    /// Sends first `tx_bits` bits of `data_out` to the tag using `communicate_thru`.
+    /// Returns the number of received bits.
+    ///
+    /// This allows sending frames which don't end on byte boundary, like 7-bit REQA.
+    /// Number of bits in the last byte is written to CIU_BitFraming (0x633D) and reset after the exchange.
+    /// CRC is still controlled by `set_crc`, so it usually should be disabled.
+    ///
+    /// Fails with `SendError::NotEnoughData` before touching any register if `data_out` is shorter than `tx_bits`.
+    pub fn transceive_bits(&mut self, data_out: &[u8], tx_bits: u16, data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let tx_bytes = (tx_bits as usize).div_ceil(8);
+        let tx_last_bits = (tx_bits % 8) as u8;
+        if tx_bytes > data_out.len() {
+            return Err(CommError::SendError(SendError::NotEnoughData { required: tx_bytes, available: data_out.len() }));
+        }
+
+        let mut bit_framing = [0u8];
+        self.read_registers(&[ciu::BIT_FRAMING], &mut bit_framing)?;
+        self.write_registers(&[(ciu::BIT_FRAMING, (bit_framing[0] & !0x07) | tx_last_bits)])?;
+
+        let result = self.communicate_thru(&data_out[..tx_bytes], data_in);
+
+        self.write_registers(&[(ciu::BIT_FRAMING, bit_framing[0] & !0x07)])?;
+        let len = result?;
+
+        // RxLastBits are in the lowest three bits of CIU_Control, 0 means whole byte.
+        let mut control = [0u8];
+        self.read_registers(&[ciu::CONTROL], &mut control)?;
+        let rx_last_bits = (control[0] & 0x07) as usize;
+        if rx_last_bits == 0 || len == 0 {
+            Ok(len * 8)
+        } else {
+            Ok((len - 1) * 8 + rx_last_bits)
         }
     }
-}
 
-impl<D: bus::WaitRead + bus::BusWrite> tags_internal::PN532Transceive for PN532<D> {
-    type TransceiveError = CommError<D::ReadError, D::WriteError>;
+    /// Configures timeouts used by PN532 (RFConfiguration item 0x02).
+    ///
+    /// `atr_res_timeout` bounds waiting for ATR_RES and `retry_timeout` bounds waiting
+    /// for response of non-DEP target during activation. Their defaults are `0x0B` and `0x0A`.
+    /// `rfu` should be `0x00`.
+    ///
+    /// Timeout code `n` means 100 µs * 2^(n - 1):
+    ///
+    /// | code | timeout  |   | code | timeout  |
+    /// |------|----------|---|------|----------|
+    /// | 0x00 | none     |   | 0x09 | 25.6 ms  |
+    /// | 0x01 | 100 µs   |   | 0x0A | 51.2 ms  |
+    /// | 0x02 | 200 µs   |   | 0x0B | 102.4 ms |
+    /// | 0x03 | 400 µs   |   | 0x0C | 204.8 ms |
+    /// | 0x04 | 800 µs   |   | 0x0D | 409.6 ms |
+    /// | 0x05 | 1.6 ms   |   | 0x0E | 819.2 ms |
+    /// | 0x06 | 3.2 ms   |   | 0x0F | 1.64 s   |
+    /// | 0x07 | 6.4 ms   |   | 0x10 | 3.28 s   |
+    /// | 0x08 | 12.8 ms  |   |      |          |
+    pub fn set_timings(&mut self, rfu: u8, atr_res_timeout: u8, retry_timeout: u8) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.rf_configuration(0x02, &[rfu, atr_res_timeout, retry_timeout])
+    }
 
-    fn transceive(&mut self, tag_number: u8, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
-        use ::std::cmp::min;
+    /// Configures numbers of retries (RFConfiguration item 0x05).
+    ///
+    /// `atr` and `psl` limit retries of ATR_REQ and PSL_REQ/ATTRIBUTE, `passive_activation`
+    /// limits retries of activating passive target while listing tags. `0xFF` means retrying
+    /// forever, `0x00` trying only once. The defaults are `0xFF`, `0x01` and `0xFF`.
+    pub fn set_max_retries(&mut self, atr: u8, psl: u8, passive_activation: u8) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.rf_configuration(0x05, &[atr, psl, passive_activation])?;
+        self.max_retries = [atr, psl, passive_activation];
+        Ok(())
+    }
 
-        let mut buf = [0u8; 256];
-        buf[0] = 0x40;
-        buf[1] = tag_number.into();
-        let to_copy = min(buf.len(), data_out.len());
-        buf[2..(2 + to_copy)].copy_from_slice(&data_out[0..to_copy]);
+    /// Returns true if PN532 currently generates RF field.
+    ///
+    /// Reads Tx1RFEn and Tx2RFEn bits of CIU_TxControl register (0x6304), so the field
+    /// isn't affected. Note that `GeneralStatus::field_present` reports external field
+    /// instead, see `is_external_field_present`.
+    pub fn is_field_on(&mut self) -> CommResult<bool, D::ReadError, D::WriteError> {
+        const TX_RF_EN: u8 = 0x03;
+
+        let mut tx_control = [0u8];
+        self.read_registers(&[ciu::TX_CONTROL], &mut tx_control)?;
+        Ok(tx_control[0] & TX_RF_EN != 0)
+    }
 
-        try!(self.device.send_wait_ack(&buf[..(2 + to_copy)]));
-        let len = try!(self.device.recv_reply_ack(&mut buf));
+    /// Reads CIU status registers useful for monitoring the module in the field.
+    ///
+    /// This is best effort, the registers only tell whether the chip overheated and whether
+    /// the field is on, see `ModuleHealth` for what isn't available.
+    pub fn health(&mut self) -> CommResult<ModuleHealth, D::ReadError, D::WriteError> {
+        let mut values = [0u8; 4];
+        self.read_registers(&[ciu::ERROR, ciu::STATUS_1, ciu::STATUS_2, ciu::TX_CONTROL], &mut values)?;
+        Ok(ModuleHealth {
+            rf_errors: RfErrorFlags { bits: values[0] },
+            status_1: values[1],
+            status_2: values[2],
+            tx_control: values[3],
+        })
+    }
 
-        // TODO: check buf[0] == 0x41 && buf[1] is status OK
-        let to_copy = min(len, data_in.len());
-        data_in[0..to_copy].copy_from_slice(&buf[2..(2 + to_copy)]);
+    /// Returns true if external RF field was detected, as reported by GetGeneralStatus.
+    ///
+    /// This is relevant in target and card emulation mode.
+    pub fn is_external_field_present(&mut self) -> CommResult<bool, D::ReadError, D::WriteError> {
+        Ok(self.general_status()?.field_present)
+    }
 
-        Ok(to_copy)
+    /// Switches the RF field off and on again (RFConfiguration item 0x01).
+    ///
+    /// Tags lose power, so they forget their state, including being halted.
+    pub fn reset_rf_field(&mut self) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::reset_rf_field(self)
+    }
+
+    // Sends RFConfiguration command with given item and its data.
+    fn rf_configuration(&mut self, item: u8, data: &[u8]) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::rf_configuration(self, item, data)
+    }
+
+    /// Runs one of the self tests of PN532 (command Diagnose).
+    ///
+    /// `params` are test-specific input parameters, at most 252 bytes. Output of the test
+    /// is written to `result` and its length is returned.
+    pub fn diagnose(&mut self, test: u8, params: &[u8], result: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        executor::diagnose(self, test, params, result)
+    }
+
+    /// Checks that the current drawn by antenna is within given thresholds.
+    pub fn antenna_test(&mut self, thresholds: AntennaThresholds) -> CommResult<AntennaHealth, D::ReadError, D::WriteError> {
+        let mut result = [0u8];
+        let len = self.diagnose(0x07, &[thresholds.code()], &mut result)?;
+        if len > 0 {
+            Ok(AntennaHealth { result: result[0] })
+        } else {
+            Err(CommError::RecvError(RecvError::UnexpectedEnd))
+        }
+    }
+
+    /// Sends command frame with data `cmd` and waits for ACK from PN532.
+    ///
+    /// Together with `recv_reply_ack` this allows sending commands which aren't wrapped by this crate.
+    pub fn send_wait_ack(&mut self, cmd: &[u8]) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.device.send_wait_ack(cmd)
+    }
+
+    /// Receives reply to the command sent by `send_wait_ack` and acknowledges it.
+    ///
+    /// Doesn't consume leading ACK, that's done by `send_wait_ack` already.
+    /// The reply starts with response code, which is command code + 1.
+    pub fn recv_reply_ack(&mut self, data: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        self.device.recv_reply_ack(data)
+    }
+
+    /// Same as `recv_reply_ack`, but reads the raw frame into `scratch` instead of internal stack buffer.
+    ///
+    /// `scratch` of `FRAME_BUF_LEN + 1` bytes fits any frame.
+    pub fn recv_reply_ack_into(&mut self, scratch: &mut [u8], data: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        self.device.recv_reply_ack_into(scratch, data)
+    }
+
+    // Sends command, receives the reply and checks that the response code matches the command.
+    // Returns the length of data following the response code, which is copied to `response`.
+    fn command(&mut self, cmd: &[u8], response: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        use ::core::cmp::min;
+
+        let mut buf = [0u8; FRAME_BUF_LEN + 1];
+        self.device.send_wait_ack(cmd)?;
+        let len = self.device.recv_reply_ack_in_place(&mut buf)?;
+        let header = ResponseHeader::parse(&buf[..len], cmd[0])?;
+
+        let to_copy = min(header.payload_len, response.len());
+        response[..to_copy].copy_from_slice(&buf[1..(1 + to_copy)]);
+        Ok(to_copy)
+    }
+
+    /// Activates single ISO14443A tag, authenticates `block` using `key` and reads it.
+    ///
+    /// This is the common flow of reading MIFARE Classic tags. Returns `Ok(None)`
+    /// if PN532 didn't find any tag. SAM must be configured before calling this.
+    #[cfg(feature = "tags")]
+    pub fn read_mifare_block(&mut self, block: u8, key_type: MifareKeyType, key: &[u8; 6]) -> CommResult<Option<[u8; 16]>, D::ReadError, D::WriteError> {
+        use self::tags_internal::{ISO14443AListOptions, TagNumLimit};
+
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        let mut buf = TagBuffer::new();
+        let tags = self.list_tags(options, &mut buf)?;
+        if tags.count() == 0 {
+            return Ok(None);
+        }
+
+        let mut tag = tags.first();
+        tag.mifare_authenticate(block, key_type, key)?;
+        let mut data = [0u8; 16];
+        if tag.mifare_read(block, &mut data)? < data.len() {
+            return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+        }
+        Ok(Some(data))
+    }
+
+    /// Checks whether the previously activated tag is still in the field.
+    ///
+    /// This sends empty InDataExchange to the tag, which is much faster than listing tags again.
+    /// Any error reported by PN532 (usually timeout) means the tag is gone.
+    pub fn is_present(&mut self, tag_number: u8) -> CommResult<bool, D::ReadError, D::WriteError> {
+        match self.transceive(tag_number, &[], &mut []) {
+            Ok(_) => Ok(true),
+            Err(CommError::Status(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the target used by `exchange` and `is_current_present`, 1 by default.
+    ///
+    /// This is only remembered on the host side, so listing tags doesn't change it.
+    /// Fails if `tag_number` isn't 1 or 2, the only numbers PN532 assigns.
+    pub fn set_current_target(&mut self, tag_number: u8) -> Result<(), InvalidTargetNumber> {
+        if tag_number != 1 && tag_number != 2 {
+            return Err(InvalidTargetNumber(tag_number));
+        }
+        self.current_target = tag_number;
+        Ok(())
+    }
+
+    pub fn current_target(&self) -> u8 {
+        self.current_target
+    }
+
+    /// Sends data to the current target and receives its response, see `set_current_target`.
+    pub fn exchange(&mut self, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let tag_number = self.current_target;
+        self.transceive(tag_number, data_out, data_in)
+    }
+
+    /// Same as `is_present`, but checks the current target.
+    pub fn is_current_present(&mut self) -> CommResult<bool, D::ReadError, D::WriteError> {
+        let tag_number = self.current_target;
+        self.is_present(tag_number)
+    }
+
+    /// Sends data to the tag activated as `tag_number` (command InDataExchange) and receives its response.
+    ///
+    /// Data longer than `max_frame_size` are split into several frames.
+    /// Fails with `Pn532Status::TargetReleased` if there's no such active tag, e.g. after `release`.
+    /// Other rejections keep `Pn532Status::NotAcceptable`.
+    pub fn transceive(&mut self, tag_number: u8, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let mut scratch = [0u8; 262];
+        self.transceive_with_scratch(&mut scratch, tag_number, data_out, data_in)
+    }
+
+    /// Same as `transceive` but uses `scratch` instead of allocating buffers on stack.
+    ///
+    /// Useful on targets with small stack, `scratch` holds both the command frame and the raw reply,
+    /// so no other frame-sized buffer is needed. Content of `scratch` is not meaningful after the call.
+    pub fn transceive_with_scratch(&mut self, scratch: &mut [u8; 262], tag_number: u8, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let max_chunk = self.max_frame_size();
+        self.transceive_chunked(scratch, tag_number, max_chunk, data_out, data_in)
+    }
+
+    /// Exchanges data with NFC-DEP target (peer-to-peer device) activated as `target_number`.
+    ///
+    /// PN532 wraps the data into DEP information PDUs itself and also manages the packet
+    /// number and ACK/NACK/ATN supervisory PDUs, retransmitting when needed, so none
+    /// of them is visible to the host. Data longer than single frame are chained using
+    /// the MI bit in both directions, so `data_out` and `data_in` can be as long as
+    /// the DEP protocol above needs.
+    ///
+    /// Failures of DEP are reported as `CommError::Status`, e.g. `DepInvalidDeviceState`
+    /// or `TargetReleased` if the peer deselected itself.
+    pub fn dep_exchange(&mut self, target_number: u8, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let mut scratch = [0u8; 262];
+        let max_chunk = self.max_frame_size();
+        self.transceive_chunked(&mut scratch, target_number, max_chunk, data_out, data_in)
+    }
+
+    /// Sends ISO7816 APDU to the tag and collects the whole response, returning its length and the final status word.
+    ///
+    /// While the card answers with status `61 XX` (more data available), GET RESPONSE
+    /// is sent and the data are appended to `out`. The final status word (`90 00` on success)
+    /// isn't stored in `out`, other statuses are returned as well, not as errors.
+    /// If `out` is too small, `RecvError::Truncated` is returned after the chain is read.
+    ///
+    /// The chain ends early if GET RESPONSE returns no data, `61 XX` is returned then.
+    /// Each response carries at most 256 bytes, so after 256 rounds (the longest extended
+    /// APDU response) `RecvError::ChainTooLong` is returned.
+    pub fn apdu_full(&mut self, tag_number: u8, apdu: &[u8], out: &mut [u8]) -> CommResult<(usize, u16), D::ReadError, D::WriteError> {
+        use ::core::cmp::min;
+
+        const MORE_DATA: u8 = 0x61;
+        const MAX_GET_RESPONSES: usize = 256;
+
+        let mut response = [0u8; 258];
+        let mut len = self.transceive(tag_number, apdu, &mut response)?;
+        let mut total = 0;
+        let mut rounds = 0;
+        loop {
+            if len < 2 {
+                return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+            }
+            let data = &response[..(len - 2)];
+            let sw = u16::from_be_bytes([response[len - 2], response[len - 1]]);
+            if total < out.len() {
+                let to_copy = min(data.len(), out.len() - total);
+                out[total..(total + to_copy)].copy_from_slice(&data[..to_copy]);
+            }
+            total += data.len();
+
+            // Card which keeps announcing data it doesn't send would make this loop forever.
+            if response[len - 2] != MORE_DATA || (rounds > 0 && data.is_empty()) {
+                if total > out.len() {
+                    return Err(CommError::RecvError(RecvError::Truncated { copied: out.len(), total }));
+                }
+                return Ok((total, sw));
+            }
+            if rounds == MAX_GET_RESPONSES {
+                return Err(CommError::RecvError(RecvError::ChainTooLong(rounds)));
+            }
+            rounds += 1;
+            let get_response = [0x00, 0xC0, 0x00, 0x00, response[len - 1]];
+            len = self.transceive(tag_number, &get_response, &mut response)?;
+        }
+    }
+
+    // Same as `exchange_chunks`, but reports `TargetReleased` instead of `NotAcceptable`
+    // if PN532 doesn't handle the target anymore.
+    fn transceive_chunked(&mut self, scratch: &mut [u8; 262], tag_number: u8, max_chunk: usize, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        match self.exchange_chunks(scratch, tag_number, max_chunk, data_out, data_in) {
+            // NotAcceptable has other causes too (e.g. wrong DEP state), so it's confirmed by asking PN532.
+            Err(CommError::Status(Pn532Status::NotAcceptable)) => {
+                if self.general_status()?.targets().any(|target| target.tag_number == tag_number) {
+                    Err(CommError::Status(Pn532Status::NotAcceptable))
+                } else {
+                    Err(CommError::Status(Pn532Status::TargetReleased))
+                }
+            },
+            result => result,
+        }
+    }
+
+    // Sends `data_out` to the tag in chunks of at most `max_chunk` bytes, chaining them using MI bit.
+    fn exchange_chunks(&mut self, scratch: &mut [u8; 262], tag_number: u8, max_chunk: usize, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        use ::core::cmp::min;
+
+        // Set in target number if more data follows, set in status if more data is available.
+        const MORE_INFORMATION: u8 = 0x40;
+
+        let mut sent = 0;
+        let mut len = loop {
+            let to_send = min(data_out.len() - sent, max_chunk);
+            let more = sent + to_send < data_out.len();
+            {
+                let cmd = &mut scratch[DATA_OFFSET..];
+                cmd[0] = 0x40;
+                cmd[1] = if more { tag_number | MORE_INFORMATION } else { tag_number };
+                cmd[2..(2 + to_send)].copy_from_slice(&data_out[sent..(sent + to_send)]);
+            }
+            sent += to_send;
+
+            self.device.send_wait_ack_in_place(scratch, 2 + to_send)?;
+            let len = self.device.recv_reply_ack_in_place(scratch)?;
+            if !more {
+                break len;
+            }
+
+            // PN532 only confirms reception of intermediate frames.
+            check_data_exchange_reply(&scratch[..len])?;
+        };
+
+        let mut received = 0;
+        loop {
+            check_data_exchange_reply(&scratch[..len])?;
+
+            let to_copy = min(len - 2, data_in.len() - received);
+            data_in[received..(received + to_copy)].copy_from_slice(&scratch[2..(2 + to_copy)]);
+            received += to_copy;
+
+            if scratch[1] & MORE_INFORMATION == 0 {
+                break;
+            }
+
+            scratch[DATA_OFFSET] = 0x40;
+            scratch[DATA_OFFSET + 1] = tag_number;
+            self.device.send_wait_ack_in_place(scratch, 2)?;
+            len = self.device.recv_reply_ack_in_place(scratch)?;
+        }
+
+        Ok(received)
+    }
+
+    /// Aborts the command PN532 is processing, so the next command starts from known state.
+    ///
+    /// Useful after a command timed out or failed in the middle of exchange.
+    pub fn resync(&mut self) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.device.resync().map_err(Into::into)
+    }
+
+    /// If enabled, `resync_with_timeout` is called automatically when waiting for response times out.
+    pub fn set_auto_resync(&mut self, enabled: bool) {
+        self.device.set_auto_resync(enabled)
+    }
+
+    /// Sets maximum number of bytes scanned while receiving single frame (`DEFAULT_MAX_SCAN_LEN` by default).
+    ///
+    /// Receiving fails with `RecvError::ScanLimitExceeded` if no frame is found within the limit.
+    pub fn set_max_scan_len(&mut self, len: u16) {
+        self.device.set_max_scan_len(len)
+    }
+
+    /// Returns counters of errors in frames received from PN532.
+    pub fn stats(&self) -> ProtoStats {
+        self.device.stats()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.device.reset_stats()
+    }
+
+    /// Sets frame identifiers sent to (`0xD4` by default) and expected from (`0xD5`) the chip.
+    pub fn set_direction_bytes(&mut self, host_tfi: u8, device_tfi: u8) {
+        self.device.set_direction_bytes(host_tfi, device_tfi)
+    }
+
+    /// Sets bytes written before every frame, at most `MAX_PREAMBLE_LEN` of them.
+    ///
+    /// Use `[0x55, 0x00, 0x00, 0x00]` or similar to wake up PN532 connected over HSU.
+    pub fn set_preamble(&mut self, bytes: &[u8]) -> Result<(), SendError<D::WriteError>> {
+        self.device.set_preamble(bytes)
+    }
+
+    /// Sets callback invoked with raw bytes of every frame sent to or received from PN532.
+    ///
+    /// Meant for protocol analyzers, e.g. capturing the communication to a file.
+    #[cfg(feature = "std")]
+    pub fn set_trace(&mut self, trace: TraceFn) {
+        self.device.set_trace(trace)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn clear_trace(&mut self) {
+        self.device.clear_trace()
+    }
+
+    /// Wakes PN532 up after power down, in the way needed by the bus.
+    ///
+    /// Call it before the first command following power down. It's a no-op on buses
+    /// which don't need any wakeup.
+    pub fn wakeup(&mut self) -> Result<(), SendError<D::WriteError>> {
+        self.device.wakeup()
+    }
+
+    /// Kind of the bus PN532 is connected through, e.g. for handling quirks specific to it.
+    pub fn bus_kind(&self) -> bus::BusKind {
+        self.device.bus_kind()
+    }
+
+    /// Maximum length of data that can be sent to tag in single frame.
+    /// Longer data are split into multiple frames by `transceive`.
+    pub fn max_frame_size(&self) -> usize {
+        // InDataExchange command code and target number
+        self.device.max_data_len() - 2
+    }
+
+    /// Releases the tag with number `tag_number` (command InRelease), or all tags if it's zero.
+    ///
+    /// PN532 deselects ISO14443-4 tags and halts the others, then forgets about them.
+    pub fn release(&mut self, tag_number: u8) -> CommResult<(), D::ReadError, D::WriteError> {
+        let mut buf = [0u8; 1];
+        let len = self.command(&[0x52, tag_number], &mut buf)?;
+        if len == 0 {
+            return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+        }
+        match Pn532Status::from_status_byte(buf[0]) {
+            Some(status) => Err(CommError::Status(status)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "tags")]
+impl<D: bus::WaitRead + bus::BusWrite> PN532<D> {
+    pub fn list_tags<'buf, 's, O: TagListOptions<'buf>>(&'s mut self, options: O, buf: &'buf mut TagBuffer) -> CommResult<Tags<'s, 'buf, O::Response, Self>, D::ReadError, D::WriteError> {
+        self.list_tags_into(&options, buf)?;
+
+        unsafe {
+            Ok(Tags::new(buf, self))
+        }
+    }
+
+    /// Same as `list_tags`, but retries up to `attempts` times in total if no tag was activated.
+    ///
+    /// At least one attempt is always made. Attempts which ended with timeout status or found no tag are retried after waiting
+    /// `backoff`, which doubles after each attempt. Other errors are returned immediately.
+    /// The result of the last attempt is returned if none of them succeeded.
+    #[cfg(feature = "std")]
+    pub fn list_tags_retry<'buf, 's, O: TagListOptions<'buf>>(&'s mut self, options: O, buf: &'buf mut TagBuffer, attempts: u32, backoff: ::std::time::Duration) -> CommResult<Tags<'s, 'buf, O::Response, Self>, D::ReadError, D::WriteError> {
+        use ::std::time::Instant;
+        use bus::busy_wait::Timer;
+
+        let attempts = ::core::cmp::max(attempts, 1);
+        let mut delay = backoff;
+        for attempt in 1..=attempts {
+            let res = self.list_tags_into(&options, buf);
+            let retry = match res {
+                Ok(()) => buf.raw_mut()[1] == 0,
+                Err(CommError::Status(Pn532Status::Timeout)) => true,
+                Err(_) => false,
+            };
+            if !retry || attempt == attempts {
+                res?;
+                break;
+            }
+            <Instant as Timer>::wait(&delay);
+            delay = delay.checked_mul(2).unwrap_or(delay);
+        }
+
+        unsafe {
+            Ok(Tags::new(buf, self))
+        }
+    }
+
+    /// Lists ISO14443A tags which weren't in `buf` yet, allowing inventory of more than two tags.
+    ///
+    /// PN532 handles at most two tags at once, so more tags are listed in batches:
+    /// call `list_tags` first and then `list_more_tags` with the same buffer until it finds no tag.
+    /// Each call releases the previous batch by InRelease, which halts the tags, so they
+    /// don't respond to REQA of the following InListPassiveTarget. Tags responding again anyway
+    /// (e.g. because `wake_up` is set) are recognized by UID and dropped from the result.
+    /// Only the previous batch is compared, tags from older batches are expected to stay halted.
+    ///
+    /// Tags of the previous batch can't be communicated with anymore.
+    pub fn list_more_tags<'buf, 's>(&'s mut self, options: ISO14443AListOptions, buf: &'buf mut TagBuffer) -> CommResult<Tags<'s, 'buf, ISO14443A<'buf>, Self>, D::ReadError, D::WriteError> {
+        // UIDs of previous batch with their lengths
+        let mut previous = [(0usize, [0u8; 10]); 2];
+        let mut previous_count = 0;
+        for (info, prev) in buf.iso14443a_infos().zip(previous.iter_mut()) {
+            let id = info.id();
+            if id.len() <= prev.1.len() {
+                prev.0 = id.len();
+                prev.1[..id.len()].copy_from_slice(id);
+                previous_count += 1;
+            }
+        }
+        let previous = &previous[..previous_count];
+
+        self.release(0)?;
+        self.list_tags_into(&options, buf)?;
+        buf.retain_iso14443a(|info| !previous.iter().any(|&(len, ref id)| id[..len] == *info.id()));
+
+        unsafe {
+            Ok(Tags::new(buf, self))
+        }
+    }
+
+    // Sends the listing command and stores the validated reply into `buf`.
+    fn list_tags_into<'buf, O: TagListOptions<'buf>>(&mut self, options: &O, buf: &mut TagBuffer) -> CommResult<(), D::ReadError, D::WriteError> {
+        executor::list_tags(self, options, buf)
+    }
+}
+
+impl<D: bus::WaitRead + bus::WaitReadTimeout + bus::BusWrite> PN532<D> where D::Duration: Clone {
+    /// Same as `resync`, but also discards frames PN532 sends until it stays quiet for `quiet` time.
+    ///
+    /// Needed when the response to the aborted command may be already on its way.
+    /// Fails with `RecvError::StillStreaming` if PN532 doesn't go quiet after few frames.
+    pub fn resync_with_timeout(&mut self, quiet: D::Duration) -> WaitResult<(), CommError<D::ReadError, D::WriteError>, D::Duration> {
+        self.device.resync_with_timeout(quiet)
+    }
+
+    /// Same as `recv_reply_ack`, but gives up waiting for the reply after `timeout`.
+    ///
+    /// If auto resync is enabled, the command is aborted on timeout, see `set_auto_resync`.
+    pub fn recv_reply_ack_timeout(&mut self, data: &mut [u8], timeout: D::Duration) -> WaitResult<usize, CommError<D::ReadError, D::WriteError>, D::Duration> {
+        let len = self.device.recv_with_timeout(data, timeout).map_err(|e| e.map(CommError::from))?;
+        self.device.send_ack().map_err(CommError::from)?;
+        Ok(len)
+    }
+}
+
+// Result of `PN532::list_tags_cancellable`.
+#[cfg(feature = "tags")]
+type CancellableTags<'s, 'buf, R, D> = WaitResult<Tags<'s, 'buf, R, PN532<D>>, CommError<<D as bus::WaitRead>::ReadError, <D as bus::BusWrite>::WriteError>, <D as bus::WaitReadTimeout>::Duration>;
+
+#[cfg(feature = "tags")]
+impl<D: bus::WaitRead + bus::WaitReadTimeout + bus::BusWrite> PN532<D> where D::Duration: Clone {
+    /// Same as `list_tags`, but gives up waiting for tags after `timeout` or once `cancel` is cancelled.
+    ///
+    /// In both cases the command is aborted by sending ACK, so PN532 is ready for next command.
+    pub fn list_tags_cancellable<'buf, 's, O: TagListOptions<'buf>>(&'s mut self, options: O, buf: &'buf mut TagBuffer, timeout: D::Duration, cancel: &bus::CancelToken) -> CancellableTags<'s, 'buf, O::Response, D> {
+        if options.wake_up() {
+            self.reset_rf_field()?;
+        }
+
+        let reply_len = {
+            let raw_buf = buf.raw_mut();
+            raw_buf[0] = options.command_code();
+            let len = options.fill_buf(&mut raw_buf[1..]);
+
+            self.device.send_wait_ack(&raw_buf[..(1 + len)])?;
+            let mut scratch = [0u8; FRAME_BUF_LEN + 1];
+            let len = match self.device.recv_cancellable_into(&mut scratch, raw_buf as &mut [u8], timeout, cancel) {
+                Ok(len) => len,
+                Err(WaitError::OtherError(e)) => return Err(CommError::from(e).into()),
+                Err(e) => {
+                    self.device.send_ack().map_err(CommError::from)?;
+                    return Err(e.map(CommError::from));
+                },
+            };
+            self.device.send_ack().map_err(CommError::from)?;
+            check_list_reply(&raw_buf[..len], options.command_code())?;
+            len
+        };
+        buf.set_len(reply_len);
+
+        unsafe {
+            Ok(Tags::new(buf, self))
+        }
+    }
+
+    /// Checks whether there's ISO14443A tag in the field right now, without waiting for one to appear.
+    ///
+    /// Passive activation is tried only once, so this returns quickly even if there's no tag,
+    /// `timeout` only guards against PN532 not responding. The found tag stays activated
+    /// as tag number 1, so it can be used e.g. by `transceive`. Retries set by `set_max_retries`
+    /// are restored afterwards.
+    pub fn try_detect(&mut self, timeout: D::Duration) -> CommResult<bool, D::ReadError, D::WriteError> {
+        use self::tags_internal::TagNumLimit;
+        use ::core::sync::atomic::AtomicBool;
+
+        // Never set, so the listing is bounded only by `timeout`.
+        static NOT_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+        let retries = self.max_retries;
+        self.rf_configuration(0x05, &[retries[0], retries[1], 0x00])?;
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        let mut buf = TagBuffer::new();
+        let found = match self.list_tags_cancellable(options, &mut buf, timeout, &bus::CancelToken::from_static(&NOT_CANCELLED)) {
+            Ok(tags) => Ok(tags.count() > 0),
+            Err(WaitError::Timeout(_)) | Err(WaitError::Cancelled) => Ok(false),
+            Err(WaitError::OtherError(e)) => Err(e),
+        };
+        // Error of the listing is more relevant than the one of restoring.
+        let restored = self.rf_configuration(0x05, &retries);
+        let found = found?;
+        restored?;
+        Ok(found)
+    }
+
+    /// Waits up to `timeout` for a card of any supported type and returns information about it.
+    ///
+    /// Returns `Ok(None)` if no card appears in time. If more cards are present,
+    /// only the first one is returned. SAM must be configured before polling.
+    #[cfg(feature = "std")]
+    pub fn poll_once(&mut self, timeout: D::Duration) -> CommResult<Option<DetectedCard>, D::ReadError, D::WriteError> {
+        use self::tags_internal::{AutoPollOptions, PollTarget, ISO14443AListOptions, TagNumLimit};
+
+        if self.quirks().no_auto_poll {
+            let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+            let mut buf = TagBuffer::new();
+            return match self.list_tags_cancellable(options, &mut buf, timeout, &bus::CancelToken::new()) {
+                Ok(tags) if tags.count() > 0 => Ok(Some(DetectedCard::ISO14443A(tags.first().to_owned()))),
+                Ok(_) | Err(WaitError::Timeout(_)) | Err(WaitError::Cancelled) => Ok(None),
+                Err(WaitError::OtherError(e)) => Err(e),
+            };
+        }
+
+        const TARGETS: [PollTarget; 5] = [PollTarget::ISO14443A, PollTarget::FeliCa212, PollTarget::FeliCa424, PollTarget::ISO14443B, PollTarget::Jewel];
+
+        // Poll until the timeout aborts the command, in rounds of 150 ms.
+        let options = AutoPollOptions { poll_count: 0xFF, period: 1, targets: &TARGETS };
+        let mut buf = TagBuffer::new();
+        match self.list_tags_cancellable(options, &mut buf, timeout, &bus::CancelToken::new()) {
+            Ok(tags) if tags.count() > 0 => Ok(Some(tags.first().to_detected())),
+            Ok(_) | Err(WaitError::Timeout(_)) | Err(WaitError::Cancelled) => Ok(None),
+            Err(WaitError::OtherError(e)) => Err(e),
+        }
+    }
+}
+
+#[cfg(feature = "tags")]
+impl<D: bus::WaitRead + bus::BusWrite> tags_internal::PN532Transceive for PN532<D> {
+    type TransceiveError = CommError<D::ReadError, D::WriteError>;
+
+    fn transceive(&mut self, tag_number: u8, data_out: &[u8], data_in: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        PN532::transceive(self, tag_number, data_out, data_in)
+    }
+
+    fn transceive_fragmented(&mut self, tag_number: u8, max_frame_size: usize, data_to_tag: &[u8], data_from_tag: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        use ::core::cmp::min;
+
+        let mut scratch = [0u8; 262];
+        let max_chunk = min(max_frame_size, self.max_frame_size());
+        self.transceive_chunked(&mut scratch, tag_number, max_chunk, data_to_tag, data_from_tag)
+    }
+
+    fn max_frame_size(&self) -> usize {
+        PN532::max_frame_size(self)
+    }
+
+    fn halt_iso14443a(&mut self) -> CommResult<(), D::ReadError, D::WriteError> {
+        PN532::halt_iso14443a(self)
+    }
+
+    fn target_status(&mut self, tag_number: u8) -> CommResult<Option<TargetStatus>, D::ReadError, D::WriteError> {
+        Ok(self.general_status()?.targets().find(|target| target.tag_number == tag_number).cloned())
+    }
+
+    fn set_baudrate(&mut self, tag_number: u8, baudrate: Baudrate) -> CommResult<bool, D::ReadError, D::WriteError> {
+        match self.psl(tag_number, baudrate, baudrate) {
+            Ok(()) => Ok(true),
+            Err(CommError::Status(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Checks response code and number of tags of InListPassiveTarget (or InAutoPoll) reply,
+// so that `Tags` aren't built from garbage.
+#[cfg(feature = "tags")]
+fn check_list_reply<R: ::core::error::Error, W: ::core::error::Error>(reply: &[u8], command_code: u8) -> CommResult<(), R, W> {
+    if ResponseHeader::parse(reply, command_code)?.payload_len < 1 {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+    if reply[1] > 2 {
+        return Err(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(reply[1], "number of tags at most 2"))));
+    }
+    Ok(())
+}
+
+// Checks response code and status of InDataExchange reply.
+fn check_data_exchange_reply<R: ::core::error::Error, W: ::core::error::Error>(reply: &[u8]) -> CommResult<(), R, W> {
+    if ResponseHeader::parse(reply, 0x40)?.payload_len < 1 {
+        return Err(CommError::RecvError(RecvError::UnexpectedEnd));
+    }
+    match Pn532Status::from_status_byte(reply[1]) {
+        Some(status) => Err(CommError::Status(status)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::std::io;
+    use ::std::collections::VecDeque;
+    use ::std::time::Duration;
+    use ::bus::{WaitRead, WaitReadTimeout, BusWrite};
+    use ::error::{WaitResult, WaitError};
+    use super::PN532;
+
+    // Replays prepared reads and records writes.
+    struct Mock {
+        reads: VecDeque<Vec<u8>>,
+        writes: Vec<Vec<u8>>,
+        max_write_len: usize,
+    }
+
+    impl Mock {
+        fn new() -> Self {
+            Mock {
+                reads: VecDeque::new(),
+                writes: Vec::new(),
+                max_write_len: usize::MAX,
+            }
+        }
+
+        // Prepares ACK followed by response frame with given data
+        fn reply(&mut self, data: &[u8]) {
+            self.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+
+            let mut frame = vec![0x01, 0x00, 0x00, 0xFF, data.len() as u8 + 1, 0u8.wrapping_sub(data.len() as u8 + 1), 0xD5];
+            frame.extend_from_slice(data);
+            let sum = data.iter().fold(0xD5u8, |a, b| a.wrapping_add(*b));
+            frame.push(0u8.wrapping_sub(sum));
+            frame.push(0x00);
+            self.reads.push_back(frame);
+        }
+
+        // Returns data of command frames sent by host, skipping ACKs
+        fn commands(&self) -> Vec<Vec<u8>> {
+            self.writes.iter()
+                .filter(|w| w.len() > 6)
+                .map(|w| w[6..(w.len() - 2)].to_vec())
+                .collect()
+        }
+    }
+
+    impl WaitRead for Mock {
+        type ReadError = io::Error;
+
+        fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            let read = self.reads.pop_front().expect("unexpected read");
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            buf[..read.len()].copy_from_slice(&read);
+            Ok(buf.len())
+        }
+    }
+
+    // Prepared empty read means timeout.
+    impl WaitReadTimeout for Mock {
+        type Duration = Duration;
+
+        fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> WaitResult<usize, io::Error> {
+            if self.reads.front().is_some_and(Vec::is_empty) {
+                self.reads.pop_front();
+                Err(WaitError::Timeout(timeout))
+            } else {
+                self.wait_read(buf).map_err(Into::into)
+            }
+        }
+    }
+
+    impl BusWrite for Mock {
+        type WriteError = io::Error;
+
+        fn write(&mut self, buf: &[u8]) -> Result<(), io::Error> {
+            self.writes.push(buf.to_vec());
+            Ok(())
+        }
+
+        fn max_write_len(&self) -> usize {
+            self.max_write_len
+        }
+
+        // Recorded as empty write, which commands() skips.
+        fn wakeup(&mut self) -> Result<(), io::Error> {
+            self.writes.push(Vec::new());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn firmware_version() {
+        use super::IcKind;
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x03, 0x32, 0x01, 0x06, 0x07]);
+        mock.reply(&[0x03, 0x33, 0x01, 0x06]);
+
+        let mut pn532 = PN532::new(mock);
+        let version = pn532.firmware_version().unwrap();
+        assert_eq!((version.ver, version.rev, version.support), (0x01, 0x06, 0x07));
+        assert_eq!(version.ic_kind(), IcKind::PN532);
+        assert!(version.is_pn532());
+        assert_matches!(pn532.firmware_version().unwrap_err(), ::error::CommError::RecvError(::error::RecvError::UnexpectedEnd));
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x02], vec![0x02]]);
+    }
+
+    #[cfg(all(feature = "std", feature = "tags"))]
+    #[test]
+    fn quirks() {
+        use super::{Quirks, SAMMode, SamTimeout};
+        use device::tags_internal::DetectedCard;
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x03, 0x33, 0x02, 0x07, 0x07]);
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x44, 0x00, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        mock.reply(&[0x15]);
+
+        let mut pn532 = PN532::new(mock);
+        assert_eq!(pn532.quirks(), Quirks::default());
+        pn532.firmware_version().unwrap();
+        assert_eq!(pn532.quirks(), Quirks { no_sam: true, no_auto_poll: true });
+
+        pn532.sam_configure(SAMMode::Normal(None)).unwrap();
+        assert_eq!(pn532.sam_mode(), Some(SAMMode::Normal(None)));
+        assert_matches!(pn532.poll_once(Duration::from_secs(1)).unwrap(), Some(DetectedCard::ISO14443A(_)));
+        // Modes needing SAM are left to the chip to reject.
+        pn532.sam_configure(SAMMode::VirtualCard(SamTimeout::new(0x14).unwrap())).unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x02], vec![0x4A, 0x01, 0x00], vec![0x14, 0x02, 0x14, 0x01]]);
+    }
+
+    #[test]
+    fn sam_mode() {
+        use super::{SAMMode, SamTimeout};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x15]);
+        mock.reply(&[0x16]);
+
+        let mut pn532 = PN532::new(mock);
+        assert_eq!(pn532.sam_mode(), None);
+        pn532.sam_configure(SAMMode::Normal(None)).unwrap();
+        assert_eq!(pn532.sam_mode(), Some(SAMMode::Normal(None)));
+        pn532.sam_configure(SAMMode::VirtualCard(SamTimeout::new(0x14).unwrap())).unwrap_err();
+        assert_eq!(pn532.sam_mode(), Some(SAMMode::Normal(None)));
+    }
+
+    #[test]
+    fn sam_configure_frames() {
+        use super::{SAMMode, SamTimeout};
+
+        let timeout = SamTimeout::new(0x14).unwrap();
+        let mut mock = Mock::new();
+        for _ in 0..5 {
+            mock.reply(&[0x15]);
+        }
+
+        let mut pn532 = PN532::new(mock);
+        pn532.sam_configure(SAMMode::Normal(None)).unwrap();
+        pn532.sam_configure(SAMMode::Normal(Some(timeout))).unwrap();
+        pn532.sam_configure(SAMMode::VirtualCard(timeout)).unwrap();
+        pn532.sam_configure(SAMMode::WiredCard(None)).unwrap();
+        pn532.sam_configure(SAMMode::DualCard(Some(SamTimeout::MAX))).unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![
+            vec![0x14, 0x01, 0x01],
+            vec![0x14, 0x01, 0x14, 0x01],
+            vec![0x14, 0x02, 0x14, 0x01],
+            vec![0x14, 0x03, 0x01],
+            vec![0x14, 0x04, 0xFF, 0x01],
+        ]);
+    }
+
+    #[test]
+    fn sam_mode_constructors() {
+        use super::{SAMMode, SamTimeout};
+
+        let timeout = SamTimeout::new(0x14).unwrap();
+        assert_eq!(SAMMode::normal(), SAMMode::Normal(None));
+        assert_eq!(SAMMode::virtual_card(timeout), SAMMode::VirtualCard(timeout));
+        assert_eq!(SAMMode::wired_card().with_timeout(timeout), SAMMode::WiredCard(Some(timeout)));
+        assert_eq!(SAMMode::dual_card(), SAMMode::DualCard(None));
+        assert_eq!(SAMMode::virtual_card(timeout).with_timeout(SamTimeout::MAX), SAMMode::VirtualCard(SamTimeout::MAX));
+    }
+
+    #[test]
+    fn sam_timeout() {
+        use super::SamTimeout;
+
+        assert_eq!(SamTimeout::new(0), None);
+        assert_eq!(SamTimeout::from_millis(0), None);
+        assert_eq!(SamTimeout::from_millis(1).unwrap().units(), 1);
+        assert_eq!(SamTimeout::from_millis(1000).unwrap().units(), 20);
+        assert_eq!(SamTimeout::from_millis(12750), Some(SamTimeout::MAX));
+        assert_eq!(SamTimeout::from_millis(12751), None);
+        assert_eq!(SamTimeout::MAX.as_millis(), 12750);
+    }
+
+    #[test]
+    fn nfcid3() {
+        use super::Nfcid3;
+
+        assert_eq!(Nfcid3::from_slice(&[1; 10]), Some(Nfcid3([1; 10])));
+        assert_eq!(Nfcid3::from_slice(&[1; 9]), None);
+        assert_eq!(Nfcid3::from_slice(&[1; 11]), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn nfcid3_random() {
+        use super::Nfcid3;
+
+        assert_ne!(Nfcid3::random(), Nfcid3::random());
+    }
+
+    #[test]
+    fn general_status() {
+        use super::{Baudrate, TargetStatus, SamStatus};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x05, 0x00, 0x01, 0x01, 0x01, 0x02, 0x01, 0x00, 0x80]);
+        mock.reply(&[0x05, 0x00, 0x00, 0x00, 0x00]);
+        mock.reply(&[0x05, 0x00, 0x00, 0x01, 0x01, 0x03, 0x00, 0x00, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let status = pn532.general_status().unwrap();
+        assert!(status.field_present);
+        assert_eq!(status.sam_status, 0x80);
+        assert_eq!(status.sam(), SamStatus::NegativePulse);
+        assert!(status.sam().is_fault());
+        let targets = status.targets().collect::<Vec<_>>();
+        assert_eq!(targets, vec![&TargetStatus { tag_number: 1, rx_baudrate: Baudrate::Br424, tx_baudrate: Baudrate::Br212, modulation: 0x00 }]);
+        assert_eq!(targets[0].rx_baudrate.kbps(), 424);
+
+        let status = pn532.general_status().unwrap();
+        assert_eq!(status.targets().count(), 0);
+        assert_eq!(status.sam(), SamStatus::Normal);
+        assert_matches!(pn532.general_status().unwrap_err(), ::error::CommError::RecvError(::error::RecvError::InvalidData(_)));
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x04], vec![0x04], vec![0x04]]);
+    }
+
+    #[test]
+    fn custom_command() {
+        use ::error::{CommError, RecvError};
+        use super::Frame;
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x03, 0x32, 0x01, 0x06, 0x07]);
+        mock.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = [0u8; 8];
+        pn532.send_wait_ack(&[0x02]).unwrap();
+        assert_eq!(pn532.recv_reply_ack(&mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], [0x03, 0x32, 0x01, 0x06, 0x07]);
+        assert_eq!(pn532.device.device().writes.last().unwrap(), &[0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+
+        assert_matches!(pn532.recv_reply_ack(&mut buf).unwrap_err(), CommError::RecvError(RecvError::UnexpectedFrame(Frame::Ack)));
+    }
+
+    #[test]
+    fn configured() {
+        use super::SAMMode;
+        use super::configured::Unconfigured;
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x16]);
+        mock.reply(&[0x15]);
+
+        let unconfigured = Unconfigured::new(mock);
+        let unconfigured = unconfigured.sam_configure(SAMMode::Normal(None)).map(|_| ()).unwrap_err().pn532;
+        let configured = unconfigured.sam_configure(SAMMode::Normal(None)).map_err(|err| err.error).unwrap();
+        assert_eq!(configured.sam_mode(), Some(SAMMode::Normal(None)));
+    }
+
+    #[test]
+    fn set_timings() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x33]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.set_timings(0x00, 0x0B, 0x0A).unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x32, 0x02, 0x00, 0x0B, 0x0A]]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn list_wake_up() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x33]);
+        mock.reply(&[0x33]);
+        mock.reply(&[0x4B, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: true };
+        {
+            let tags = pn532.list_tags(options, &mut buf).unwrap();
+            assert_eq!(tags.count(), 0);
+            assert_eq!(tags.raw_len(), 2);
+        }
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x32, 0x01, 0x00], vec![0x32, 0x01, 0x01], vec![0x4A, 0x01, 0x00]]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn list_error_reply() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+        use error::{CommError, RecvError, DataError};
+
+        let mut mock = Mock::new();
+        mock.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+        mock.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x01, 0xFF, 0x7F, 0x81, 0x00]);
+        mock.reply(&[0x4D, 0x00]);
+        mock.reply(&[0x4B, 0x05]);
+        mock.reply(&[0x4B]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        assert_matches!(pn532.list_tags(options, &mut buf).err(), Some(CommError::RecvError(RecvError::ErrorFrame)));
+        assert_matches!(pn532.list_tags(options, &mut buf).err(), Some(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(0x4D, _)))));
+        assert_matches!(pn532.list_tags(options, &mut buf).err(), Some(CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(0x05, _)))));
+        assert_matches!(pn532.list_tags(options, &mut buf).err(), Some(CommError::RecvError(RecvError::UnexpectedEnd)));
+    }
+
+    #[cfg(all(feature = "std", feature = "tags"))]
+    #[test]
+    fn list_cancellable() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+        use bus::CancelToken;
+
+        let ack = vec![0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        let cancel = CancelToken::new();
+        let mut mock = Mock::new();
+        mock.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+        mock.reads.push_back(vec![]);
+        mock.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+        mock.reply(&[0x4B, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        assert_matches!(pn532.list_tags_cancellable(options, &mut buf, Duration::from_secs(1), &cancel).err(), Some(WaitError::Timeout(_)));
+        assert_eq!(pn532.device.device().writes.last(), Some(&ack));
+
+        cancel.cancel();
+        assert_matches!(pn532.list_tags_cancellable(options, &mut buf, Duration::from_secs(1), &cancel).err(), Some(WaitError::Cancelled));
+        assert_eq!(pn532.device.device().writes.last(), Some(&ack));
+
+        cancel.reset();
+        {
+            let tags = pn532.list_tags_cancellable(options, &mut buf, Duration::from_secs(1), &cancel).unwrap();
+            assert_eq!(tags.count(), 0);
+        }
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x4A, 0x01, 0x00]; 3]);
+    }
+
+    #[test]
+    fn wakeup() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x05, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.wakeup().unwrap();
+        pn532.general_status().unwrap();
+        assert!(pn532.device.device().writes[0].is_empty());
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x04]]);
+    }
+
+    #[cfg(all(feature = "std", feature = "tags"))]
+    #[test]
+    fn poll_once() {
+        use device::tags_internal::DetectedCard;
+
+        let mut mock = Mock::new();
+        mock.reads.push_back(vec![0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]);
+        mock.reads.push_back(vec![]);
+        mock.reply(&[0x61, 0x01, 0x11, 0x13, 0x01, 0x12, 0x01, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut pn532 = PN532::new(mock);
+        assert_eq!(pn532.poll_once(Duration::from_secs(1)).unwrap(), None);
+        assert_eq!(pn532.poll_once(Duration::from_secs(1)).unwrap(), Some(DetectedCard::FeliCa { idm: [1, 2, 3, 4, 5, 6, 7, 8] }));
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x60, 0xFF, 0x01, 0x00, 0x01, 0x02, 0x03, 0x04]; 2]);
+    }
+
+    #[test]
+    fn set_crc() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x07, 0x80, 0x02]);
+        mock.reply(&[0x09]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.set_crc(false, true).unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![
+            vec![0x06, 0x63, 0x02, 0x63, 0x03],
+            vec![0x08, 0x63, 0x02, 0x00, 0x63, 0x03, 0x82],
+        ]);
+    }
+
+    #[test]
+    fn mifare_params() {
+        use super::MifareParams;
+        use ::error::UnsupportedEmulatedUid;
+
+        let params = MifareParams::new(0x0004, &[0x08, 0x12, 0x34, 0x56], 0x20).unwrap();
+        assert_eq!(params.nfcid1(), &[0x08, 0x12, 0x34, 0x56]);
+        assert_eq!(params.to_bytes(), [0x04, 0x00, 0x12, 0x34, 0x56, 0x20]);
+        assert_eq!(MifareParams::new(0x0044, &[0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06], 0x00), Err(UnsupportedEmulatedUid::Length(7)));
+        assert_eq!(MifareParams::new(0x0004, &[0xDE, 0xAD, 0xBE, 0xEF], 0x08), Err(UnsupportedEmulatedUid::FirstByte(0xDE)));
+    }
+
+    #[test]
+    fn read_register_range() {
+        use ::error::{CommError, SendError};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x07, 0x84, 0x85, 0x4D]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut out = [0u8; 4];
+        pn532.read_register_range(0x6318, 3, &mut out).unwrap();
+        assert_eq!(out, [0x84, 0x85, 0x4D, 0x00]);
+        assert_matches!(pn532.read_register_range(0x6300, 127, &mut [0u8; 127]).unwrap_err(), CommError::SendError(SendError::TooMuchData(255)));
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x06, 0x63, 0x18, 0x63, 0x19, 0x63, 0x1A]]);
+    }
+
+    #[test]
+    fn read_registers_long_reply() {
+        let values = (0..40).collect::<Vec<u8>>();
+        let mut reply = vec![0x07];
+        reply.extend_from_slice(&values);
+        let mut mock = Mock::new();
+        mock.reply(&reply);
+
+        let mut pn532 = PN532::new(mock);
+        let mut out = [0u8; 40];
+        pn532.read_register_range(0x6300, 40, &mut out).unwrap();
+        assert_eq!(out[..], values[..]);
+    }
+
+    #[test]
+    fn read_register_range_short_buffer() {
+        use ::error::{CommError, RecvError};
+
+        let mut pn532 = PN532::new(Mock::new());
+        assert_matches!(pn532.read_register_range(0x6318, 3, &mut [0u8; 2]).unwrap_err(), CommError::RecvError(RecvError::Truncated { copied: 0, total: 3 }));
+        assert!(pn532.device.device().commands().is_empty());
+    }
+
+    #[test]
+    fn set_max_retries() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x33]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.set_max_retries(0x02, 0x01, 0x10).unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x32, 0x05, 0x02, 0x01, 0x10]]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn try_detect() {
+        use ::std::time::Duration;
+        use ::error::{CommError, RecvError, DataError};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x33]);
+        mock.reply(&[0x33]);
+        mock.reply(&[0x4B, 0x00]);
+        mock.reply(&[0x33]);
+        mock.reply(&[0x33]);
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        mock.reply(&[0x33]);
+        // Listing times out
+        mock.reply(&[0x33]);
+        mock.reply(&[0x4B, 0x00]);
+        mock.reads.pop_back();
+        mock.reads.push_back(Vec::new());
+        mock.reply(&[0x33]);
+        // Both listing and restoring fail
+        mock.reply(&[0x33]);
+        mock.reply(&[0x4D, 0x00]);
+        mock.reply(&[0x35]);
+
+        let timeout = Duration::from_millis(10);
+        let mut pn532 = PN532::new(mock);
+        pn532.set_max_retries(0x05, 0x02, 0x10).unwrap();
+        assert!(!pn532.try_detect(timeout).unwrap());
+        assert!(pn532.try_detect(timeout).unwrap());
+        assert!(!pn532.try_detect(timeout).unwrap());
+        assert_matches!(pn532.try_detect(timeout).unwrap_err(), CommError::RecvError(RecvError::InvalidData(DataError::InvalidByte(0x4D, _))));
+        let commands = pn532.device.device().commands();
+        assert_eq!(commands[1..4], [
+            vec![0x32, 0x05, 0x05, 0x02, 0x00],
+            vec![0x4A, 0x01, 0x00],
+            vec![0x32, 0x05, 0x05, 0x02, 0x10],
+        ]);
+        assert_eq!(commands[4..7], commands[1..4]);
+        assert_eq!(commands[7..10], commands[1..4]);
+    }
+
+    #[test]
+    fn set_rx_sensitivity() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x09]);
+        mock.reply(&[0x09]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.set_rx_sensitivity(super::RxSensitivity::High).unwrap();
+        pn532.set_rx_sensitivity(super::RxSensitivity::Normal).unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![
+            vec![0x08, 0x63, 0x08, 0x55],
+            vec![0x08, 0x63, 0x08, 0x85],
+        ]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn with_busy_wait() {
+        use ::bus::{BusRead, BusWrite};
+        use ::std::cell::RefCell;
+        use ::std::io;
+        use ::std::rc::Rc;
+
+        struct Raw(Rc<RefCell<Vec<Vec<u8>>>>);
+
+        impl BusRead for Raw {
+            type ReadError = io::Error;
+
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+                buf[0] = 0;
+                Ok(buf.len())
+            }
+        }
+
+        impl BusWrite for Raw {
+            type WriteError = io::Error;
+
+            fn write(&mut self, buf: &[u8]) -> Result<(), io::Error> {
+                self.0.borrow_mut().push(buf.to_vec());
+                Ok(())
+            }
+        }
+
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let mut pn532 = PN532::with_busy_wait(Raw(writes.clone()));
+        pn532.resync().unwrap();
+        assert_eq!(*writes.borrow(), vec![vec![0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]]);
+    }
+
+    #[test]
+    fn auto_resync_drains() {
+        use ::std::time::Duration;
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x03, 0x32, 0x01, 0x06, 0x07]);
+        // Reply times out, arrives late and is discarded.
+        let stale = mock.reads.pop_back().unwrap();
+        mock.reads.push_back(Vec::new());
+        mock.reads.push_back(stale);
+        mock.reads.push_back(Vec::new());
+        mock.reply(&[0x03, 0x32, 0x01, 0x06, 0x07]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.set_auto_resync(true);
+        let mut buf = [0u8; 8];
+        pn532.send_wait_ack(&[0x02]).unwrap();
+        assert_matches!(pn532.recv_reply_ack_timeout(&mut buf, Duration::from_millis(10)), Err(WaitError::Timeout(_)));
+        assert_eq!(pn532.firmware_version().unwrap().ver, 0x01);
+        assert!(pn532.device.device().reads.is_empty());
+    }
+
+    #[test]
+    fn is_field_on() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x07, 0x83]);
+        mock.reply(&[0x07, 0x80]);
+        mock.reply(&[0x05, 0x00, 0x01, 0x00, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        assert!(pn532.is_field_on().unwrap());
+        assert!(!pn532.is_field_on().unwrap());
+        assert!(pn532.is_external_field_present().unwrap());
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x06, 0x63, 0x04], vec![0x06, 0x63, 0x04], vec![0x04]]);
+    }
+
+    #[test]
+    fn health() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x07, 0x40, 0x21, 0x00, 0x80]);
+
+        let mut pn532 = PN532::new(mock);
+        let health = pn532.health().unwrap();
+        assert!(health.overheated());
+        assert!(!health.field_on());
+        assert_eq!((health.status_1, health.status_2), (0x21, 0x00));
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x06, 0x63, 0x36, 0x63, 0x37, 0x63, 0x38, 0x63, 0x04]]);
+    }
+
+    #[test]
+    fn rf_error() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x07, 0x0C]);
+        mock.reply(&[0x09]);
+
+        let mut pn532 = PN532::new(mock);
+        let flags = pn532.read_rf_error().unwrap();
+        assert!(flags.collision() && flags.crc());
+        assert!(!flags.is_empty() && !flags.parity() && !flags.protocol() && !flags.temperature());
+        pn532.clear_rf_error().unwrap();
+        assert_eq!(pn532.device.device().commands(), vec![
+            vec![0x06, 0x63, 0x36],
+            vec![0x08, 0x63, 0x31, 0x00, 0x63, 0x3A, 0x80],
+        ]);
+    }
+
+    #[test]
+    fn communicate_thru() {
+        use ::error::{CommError, Pn532Status};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x43, 0x00, 0x04, 0x00]);
+        mock.reply(&[0x43, 0x01]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = [0u8; 8];
+        assert_eq!(pn532.communicate_thru(&[0x26], &mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [0x04, 0x00]);
+        assert_matches!(pn532.communicate_thru(&[0x26], &mut buf).unwrap_err(), CommError::Status(Pn532Status::Timeout));
+    }
+
+    #[test]
+    fn halt_iso14443a() {
+        use ::error::{CommError, Pn532Status};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x43, 0x01]);
+        mock.reply(&[0x43, 0x00, 0x04]);
+        mock.reply(&[0x43, 0x02]);
+
+        let mut pn532 = PN532::new(mock);
+        pn532.halt_iso14443a().unwrap();
+        assert_matches!(pn532.halt_iso14443a().unwrap_err(), CommError::RecvError(_));
+        assert_matches!(pn532.halt_iso14443a().unwrap_err(), CommError::Status(Pn532Status::Crc));
+        assert_eq!(pn532.device.device().commands()[0], [0x42, 0x50, 0x00]);
+    }
+
+    #[test]
+    fn transceive_bits() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x07, 0x80]);
+        mock.reply(&[0x09]);
+        mock.reply(&[0x43, 0x00, 0x04, 0x00]);
+        mock.reply(&[0x09]);
+        mock.reply(&[0x07, 0x10]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = [0u8; 8];
+        assert_eq!(pn532.transceive_bits(&[0x26], 7, &mut buf).unwrap(), 16);
+        assert_eq!(buf[..2], [0x04, 0x00]);
+        assert_eq!(pn532.device.device().commands(), vec![
+            vec![0x06, 0x63, 0x3D],
+            vec![0x08, 0x63, 0x3D, 0x87],
+            vec![0x42, 0x26],
+            vec![0x08, 0x63, 0x3D, 0x80],
+            vec![0x06, 0x63, 0x3C],
+        ]);
+
+        let mut pn532 = PN532::new(Mock::new());
+        assert_matches!(pn532.transceive_bits(&[0x26], 9, &mut buf).unwrap_err(), ::error::CommError::SendError(::error::SendError::NotEnoughData { required: 2, available: 1 }));
+        assert!(pn532.device.device().commands().is_empty());
+    }
+
+    #[test]
+    fn is_present() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x41, 0x00]);
+        mock.reply(&[0x41, 0x01]);
+
+        let mut pn532 = PN532::new(mock);
+        assert!(pn532.is_present(1).unwrap());
+        assert!(!pn532.is_present(1).unwrap());
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x40, 0x01], vec![0x40, 0x01]]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn transceive_two_tags() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x04, 0x08, 0x04, 0xCA, 0xFE, 0xBA, 0xBE]);
+        mock.reply(&[0x41, 0x00, 0x11]);
+        mock.reply(&[0x41, 0x00, 0x22]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::Two, uid: None, wake_up: false };
+        {
+            let mut data_in = [0u8; 1];
+            let mut tag = pn532.list_tags(options, &mut buf).unwrap().first();
+            tag.transceive(&[0x30, 0x00], &mut data_in).unwrap();
+            assert_eq!(data_in, [0x11]);
+            let mut tag = tag.next().unwrap();
+            assert_eq!(tag.id(), &[0xCA, 0xFE, 0xBA, 0xBE]);
+            tag.transceive(&[0x30, 0x04], &mut data_in).unwrap();
+            assert_eq!(data_in, [0x22]);
+        }
+        assert_eq!(&pn532.device.device().commands()[1..], &[vec![0x40, 0x01, 0x30, 0x00], vec![0x40, 0x02, 0x30, 0x04]]);
+    }
+
+    #[test]
+    fn current_target() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x41, 0x00, 0x11]);
+        mock.reply(&[0x41, 0x01]);
+
+        let mut pn532 = PN532::new(mock);
+        assert_eq!(pn532.current_target(), 1);
+        let mut data_in = [0u8; 1];
+        assert_eq!(pn532.exchange(&[0x30, 0x00], &mut data_in).unwrap(), 1);
+        pn532.set_current_target(2).unwrap();
+        assert!(!pn532.is_current_present().unwrap());
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x40, 0x01, 0x30, 0x00], vec![0x40, 0x02]]);
+    }
+
+    #[test]
+    fn current_target_invalid() {
+        use ::error::InvalidTargetNumber;
+
+        let mut pn532 = PN532::new(Mock::new());
+        assert_eq!(pn532.set_current_target(3), Err(InvalidTargetNumber(3)));
+        assert_eq!(pn532.set_current_target(0), Err(InvalidTargetNumber(0)));
+        assert_eq!(pn532.current_target(), 1);
+    }
+
+    #[test]
+    fn apdu_full() {
+        use ::error::{CommError, RecvError};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x41, 0x00, 0x61, 0x03]);
+        mock.reply(&[0x41, 0x00, 0x01, 0x02, 0x03, 0x61, 0x02]);
+        mock.reply(&[0x41, 0x00, 0x04, 0x05, 0x90, 0x00]);
+        mock.reply(&[0x41, 0x00, 0x6A, 0x82]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut out = [0u8; 8];
+        assert_eq!(pn532.apdu_full(1, &[0x00, 0xB0, 0x00, 0x00, 0x00], &mut out).unwrap(), (5, 0x9000));
+        assert_eq!(&out[..5], &[0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert_eq!(pn532.apdu_full(1, &[0x00, 0xA4, 0x04, 0x00], &mut out).unwrap(), (0, 0x6A82));
+        assert_eq!(&pn532.device.device().commands()[1..3], &[vec![0x40, 0x01, 0x00, 0xC0, 0x00, 0x00, 0x03], vec![0x40, 0x01, 0x00, 0xC0, 0x00, 0x00, 0x02]]);
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x41, 0x00, 0x01, 0x02, 0x61, 0x02]);
+        mock.reply(&[0x41, 0x00, 0x03, 0x04, 0x90, 0x00]);
+        let mut pn532 = PN532::new(mock);
+        assert_matches!(pn532.apdu_full(1, &[0x00, 0xB0, 0x00, 0x00, 0x00], &mut out[..3]).unwrap_err(), CommError::RecvError(RecvError::Truncated { copied: 3, total: 4 }));
+    }
+
+    #[test]
+    fn apdu_full_long_chain() {
+        let data = (0..220).collect::<Vec<u8>>();
+        let mut first = vec![0x41, 0x00];
+        first.extend_from_slice(&data[..100]);
+        first.extend_from_slice(&[0x61, 120]);
+        let mut second = vec![0x41, 0x00];
+        second.extend_from_slice(&data[100..]);
+        second.extend_from_slice(&[0x90, 0x00]);
+        let mut mock = Mock::new();
+        mock.reply(&first);
+        mock.reply(&second);
+
+        let mut pn532 = PN532::new(mock);
+        let mut out = [0u8; 256];
+        assert_eq!(pn532.apdu_full(1, &[0x00, 0xB0, 0x00, 0x00, 0x00], &mut out).unwrap(), (220, 0x9000));
+        assert_eq!(out[..220], data[..]);
+        assert_eq!(pn532.device.device().commands()[1], vec![0x40, 0x01, 0x00, 0xC0, 0x00, 0x00, 120]);
+    }
+
+    #[test]
+    fn apdu_full_empty_chain() {
+        use ::error::{CommError, RecvError};
+
+        let mut mock = Mock::new();
+        for _ in 0..50 {
+            mock.reply(&[0x41, 0x00, 0x61, 0x00]);
+        }
+        let mut pn532 = PN532::new(mock);
+        assert_eq!(pn532.apdu_full(1, &[0x00, 0xB0, 0x00, 0x00, 0x00], &mut [0u8; 16]).unwrap(), (0, 0x6100));
+        assert_eq!(pn532.device.device().commands().len(), 2);
+
+        let mut mock = Mock::new();
+        for _ in 0..258 {
+            mock.reply(&[0x41, 0x00, 0xAA, 0x61, 0x01]);
+        }
+        let mut pn532 = PN532::new(mock);
+        assert_matches!(pn532.apdu_full(1, &[0x00, 0xB0, 0x00, 0x00, 0x00], &mut [0u8; 16]).unwrap_err(), CommError::RecvError(RecvError::ChainTooLong(256)));
+        assert_eq!(pn532.device.device().commands().len(), 257);
+    }
+
+    #[test]
+    fn transceive_status() {
+        use ::error::{CommError, Pn532Status};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x41, 0x14]);
+        mock.reply(&[0x41, 0x27]);
+        mock.reply(&[0x05, 0x00, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00]);
+        mock.reply(&[0x41, 0x27]);
+        mock.reply(&[0x05, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        assert_matches!(pn532.transceive(1, &[0x30, 0x00], &mut [0u8; 16]).unwrap_err(), CommError::Status(Pn532Status::Authentication));
+        assert_matches!(pn532.transceive(1, &[0x30, 0x00], &mut [0u8; 16]).unwrap_err(), CommError::Status(Pn532Status::NotAcceptable));
+        assert_matches!(pn532.transceive(1, &[0x30, 0x00], &mut [0u8; 16]).unwrap_err(), CommError::Status(Pn532Status::TargetReleased));
+        assert_eq!(pn532.device.device().commands()[2], vec![0x04]);
+    }
+
+    #[cfg(all(feature = "std", feature = "tags"))]
+    #[test]
+    fn list_tags_retry() {
+        use ::error::CommError;
+        use ::std::time::Duration;
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x00]);
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        mock.reply(&[0x4B, 0x00]);
+        mock.reply(&[0x4B, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        assert_eq!(pn532.list_tags_retry(options, &mut buf, 3, Duration::from_millis(1)).unwrap().first().id(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(pn532.list_tags_retry(options, &mut buf, 2, Duration::from_millis(1)).unwrap().count(), 0);
+        assert_eq!(pn532.device.device().commands().len(), 4);
+
+        // Errors other than timeout aren't retried.
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x03]);
+        let mut pn532 = PN532::new(mock);
+        assert_matches!(pn532.list_tags_retry(options, &mut buf, 3, Duration::from_millis(1)).err(), Some(CommError::RecvError(_)));
+        assert_eq!(pn532.device.device().commands().len(), 1);
+    }
+
+    #[cfg(all(feature = "std", feature = "tags"))]
+    #[test]
+    fn list_tags_heap_buffer() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, HeapTagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = HeapTagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        assert_eq!(pn532.list_tags(options, &mut buf).unwrap().first().id(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn list_tags_long_reply() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x02,
+            0x01, 0x00, 0x44, 0x00, 0x07, 0x04, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x02, 0x00, 0x44, 0x00, 0x07, 0x04, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::Two, uid: None, wake_up: false };
+        let tags = pn532.list_tags(options, &mut buf).unwrap();
+        assert_eq!(tags.count(), 2);
+        assert_eq!(tags.first().next().unwrap().id(), &[0x04, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn list_more_tags() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x04, 0x08, 0x04, 0x01, 0x02, 0x03, 0x04]);
+        mock.reply(&[0x53, 0x00]);
+        mock.reply(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0x01, 0x02, 0x03, 0x04,
+            0x02, 0x00, 0x04, 0x08, 0x04, 0xCA, 0xFE, 0xBA, 0xBE]);
+        mock.reply(&[0x53, 0x00]);
+        mock.reply(&[0x4B, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::Two, uid: None, wake_up: false };
+        assert_eq!(pn532.list_tags(options, &mut buf).unwrap().count(), 2);
+        {
+            let tags = pn532.list_more_tags(options, &mut buf).unwrap();
+            assert_eq!(tags.count(), 1);
+            let tag = tags.first();
+            assert_eq!(tag.id(), &[0xCA, 0xFE, 0xBA, 0xBE]);
+            assert!(tag.next().is_none());
+        }
+        assert_eq!(pn532.list_more_tags(options, &mut buf).unwrap().count(), 0);
+        assert_eq!(pn532.device.device().commands()[1], vec![0x52, 0x00]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn read_mifare_block() {
+        use ::error::{CommError, Pn532Status};
+        use device::tags_internal::MifareKeyType;
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        mock.reply(&[0x41, 0x00]);
+        let mut block = vec![0x41, 0x00];
+        block.extend(0..16);
+        mock.reply(&block);
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        mock.reply(&[0x41, 0x14]);
+        mock.reply(&[0x4B, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let key = [0xFF; 6];
+        let data = pn532.read_mifare_block(4, MifareKeyType::A, &key).unwrap().unwrap();
+        assert_eq!(data.to_vec(), (0..16).collect::<Vec<u8>>());
+        assert_matches!(pn532.read_mifare_block(4, MifareKeyType::B, &key).unwrap_err(), CommError::Status(Pn532Status::Authentication));
+        assert_eq!(pn532.read_mifare_block(4, MifareKeyType::A, &key).unwrap(), None);
+
+        let commands = pn532.device.device().commands();
+        assert_eq!(commands[1], vec![0x40, 0x01, 0x60, 0x04, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(commands[2], vec![0x40, 0x01, 0x30, 0x04]);
+        assert_eq!(commands[4][2], 0x61);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn tag_baudrate() {
+        use super::Baudrate;
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x01]);
+        mock.reply(&[0x05, 0x00, 0x00, 0x01, 0x01, 0x02, 0x02, 0x00, 0x00]);
+        mock.reply(&[0x05, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        let mut tag = pn532.list_tags(options, &mut buf).unwrap().first();
+        assert_eq!(tag.baudrate().unwrap(), Some(Baudrate::Br424));
+        assert_eq!(tag.baudrate().unwrap(), None);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn boost_speed() {
+        use super::Baudrate;
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x01]);
+        mock.reply(&[0x4F, 0x00]);
+        mock.reply(&[0x4F, 0x01]);
+        mock.reply(&[0x05, 0x00, 0x00, 0x01, 0x01, 0x02, 0x02, 0x00, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        {
+            let mut tag = pn532.list_tags(options, &mut buf).unwrap().first();
+            assert_eq!(tag.boost_speed(Baudrate::Br424).unwrap(), Baudrate::Br424);
+            // Rejected, the rate reported by PN532 is kept.
+            assert_eq!(tag.boost_speed(Baudrate::Br212).unwrap(), Baudrate::Br424);
+        }
+        let commands = pn532.device.device().commands();
+        assert_eq!(commands[1], vec![0x4E, 0x01, 0x02, 0x02]);
+        assert_eq!(commands[2], vec![0x4E, 0x01, 0x01, 0x01]);
+    }
+
+    #[test]
+    fn iso_dep_exchange_thru() {
+        use super::IsoDepState;
+
+        let mut mock = Mock::new();
+        // S(WTX) with multiplier 5, then chained I-block
+        mock.reply(&[0x43, 0x00, 0xF2, 0x05]);
+        mock.reply(&[0x43, 0x00, 0x12, 0xAA]);
+        mock.reply(&[0x43, 0x00, 0x03, 0x90, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut state = IsoDepState::new();
+        let mut data_in = [0u8; 8];
+        assert_eq!(pn532.iso_dep_exchange_thru(&mut state, &[0x00, 0xA4], &mut data_in).unwrap(), 3);
+        assert_eq!(data_in[..3], [0xAA, 0x90, 0x00]);
+        assert_eq!(pn532.device.device().commands(), vec![
+            vec![0x42, 0x02, 0x00, 0xA4],
+            vec![0x42, 0xF2, 0x05],
+            vec![0x42, 0xA3],
+        ]);
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x43, 0x00, 0x13, 0xAA, 0xBB]);
+        mock.reply(&[0x43, 0x00, 0x02, 0x90, 0x00]);
+        let mut pn532 = PN532::new(mock);
+        assert_matches!(pn532.iso_dep_exchange_thru(&mut state, &[0x00, 0xA4], &mut data_in[..3]).unwrap_err(), ::error::CommError::RecvError(::error::RecvError::Truncated { copied: 3, total: 4 }));
+        assert_eq!(data_in[..3], [0xAA, 0xBB, 0x90]);
+        assert_eq!(pn532.device.device().commands().len(), 2);
+        assert_matches!(pn532.iso_dep_exchange_thru(&mut state, &[0x00; 253], &mut data_in).unwrap_err(), ::error::CommError::SendError(::error::SendError::TooMuchData(254)));
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn write_trailer() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer, AccessBits, SectorTrailer};
+
+        let mut mock = Mock::new();
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        mock.reply(&[0x41, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+        let trailer = SectorTrailer { key_a: [0xFF; 6], access_bits: AccessBits::TRANSPORT, gpb: 0x69, key_b: [0xFF; 6] };
+        let mut tag = pn532.list_tags(options, &mut buf).unwrap().first();
+        assert_matches!(tag.write_trailer(40, &trailer).unwrap_err(), ::error::WriteTrailerError::InvalidSector(40));
+        tag.write_trailer(1, &trailer).unwrap();
+
+        let mut expected = vec![0x40, 0x01, 0xA0, 0x07];
+        expected.extend_from_slice(&trailer.to_bytes());
+        assert_eq!(pn532.device.device().commands()[1], expected);
+    }
+
+    #[test]
+    fn dep_exchange() {
+        use ::error::{CommError, Pn532Status};
+
+        let mut mock = Mock::new();
+        // Response chained using MI bit
+        mock.reply(&[0x41, 0x40, 0xD5, 0x07]);
+        mock.reply(&[0x41, 0x00, 0x00, 0x00]);
+        mock.reply(&[0x41, 0x25]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut data_in = [0u8; 8];
+        assert_eq!(pn532.dep_exchange(1, &[0xD4, 0x06], &mut data_in).unwrap(), 4);
+        assert_eq!(data_in[..4], [0xD5, 0x07, 0x00, 0x00]);
+        assert_matches!(pn532.dep_exchange(1, &[0xD4, 0x06], &mut data_in).unwrap_err(), CommError::Status(Pn532Status::DepInvalidDeviceState));
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x40, 0x01, 0xD4, 0x06], vec![0x40, 0x01], vec![0x40, 0x01, 0xD4, 0x06]]);
+    }
+
+    #[test]
+    fn transceive_long_reply() {
+        let data = (0..200).collect::<Vec<u8>>();
+        let mut reply = vec![0x41, 0x00];
+        reply.extend_from_slice(&data);
+        let mut mock = Mock::new();
+        mock.reply(&reply);
+
+        let mut pn532 = PN532::new(mock);
+        let mut data_in = [0u8; 256];
+        assert_eq!(pn532.transceive(1, &[0x30, 0x04], &mut data_in).unwrap(), 200);
+        assert_eq!(data_in[..200], data[..]);
+    }
+
+    #[test]
+    fn transceive_with_scratch() {
+        let mut mock = Mock::new();
+        mock.reply(&[0x41, 0x00, 0xAA]);
+        mock.reply(&[0x41, 0x00, 0xBB, 0xCC]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut scratch = [0xFFu8; 262];
+        let mut data_in = [0u8; 4];
+        assert_eq!(pn532.transceive_with_scratch(&mut scratch, 1, &[0x30, 0x04], &mut data_in).unwrap(), 1);
+        assert_eq!(data_in[0], 0xAA);
+        assert_eq!(pn532.transceive_with_scratch(&mut scratch, 2, &[0x30], &mut data_in).unwrap(), 2);
+        assert_eq!(data_in[..2], [0xBB, 0xCC]);
+        assert_eq!(pn532.device.device().commands(), vec![vec![0x40, 0x01, 0x30, 0x04], vec![0x40, 0x02, 0x30]]);
+    }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn transceive_small_fsc() {
+        use device::tags_internal::{ISO14443AListOptions, TagNumLimit, TagBuffer};
+
+        let mut mock = Mock::new();
+        // ATS with FSCI 0, so the tag accepts at most 16 - 3 bytes of data
+        mock.reply(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x02, 0x00]);
+        mock.reply(&[0x41, 0x00]);
+        mock.reply(&[0x41, 0x00, 0x90, 0x00]);
+
+        let mut pn532 = PN532::new(mock);
+        let mut buf = TagBuffer::new();
+        let data_out = (0..20).collect::<Vec<u8>>();
+        {
+            let options = ISO14443AListOptions { limit: TagNumLimit::One, uid: None, wake_up: false };
+            let mut tag = pn532.list_tags(options, &mut buf).unwrap().first();
+            assert_eq!(tag.max_transceive_len(), 13);
+
+            let mut data_in = [0u8; 4];
+            assert_eq!(tag.transceive(&data_out, &mut data_in).unwrap(), 2);
+            assert_eq!(&data_in[..2], &[0x90, 0x00]);
+        }
+
+        let commands = pn532.device.device().commands();
+        let mut first = vec![0x40, 0x41];
+        first.extend_from_slice(&data_out[..13]);
+        let mut second = vec![0x40, 0x01];
+        second.extend_from_slice(&data_out[13..]);
+        assert_eq!(commands[1..], [first, second]);
+    }
+
+    #[test]
+    fn transceive_chaining() {
+        let mut mock = Mock::new();
+        mock.max_write_len = 14;
+        mock.reply(&[0x41, 0x00]);
+        mock.reply(&[0x41, 0x00]);
+        mock.reply(&[0x41, 0x40, 1, 2]);
+        mock.reply(&[0x41, 0x00, 3]);
+
+        let mut pn532 = PN532::new(mock);
+        assert_eq!(pn532.max_frame_size(), 4);
+
+        let mut data_in = [0u8; 8];
+        let len = pn532.transceive(1, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9], &mut data_in).unwrap();
+        assert_eq!(&data_in[..len], &[1, 2, 3]);
+
+        let commands = pn532.device.device().commands();
+        assert_eq!(commands, vec![
+            vec![0x40, 0x41, 0, 1, 2, 3],
+            vec![0x40, 0x41, 4, 5, 6, 7],
+            vec![0x40, 0x01, 8, 9],
+            vec![0x40, 0x01],
+        ]);
     }
 }
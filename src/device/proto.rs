@@ -1,11 +1,12 @@
 use ::bus;
-use ::error::{DataError, ChecksumType, RecvError, SendError, WaitResult, CommResult};
-use ::std::default::Default;
+use ::error::{DataError, ChecksumType, RecvError, SendError, WaitError, WaitResult, CommResult, CommError};
+use ::core::default::Default;
 
 // State machine to parse Preamble.
 // Could have been bool, but that would be less readable.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
 enum PreambleParser {
+    #[default]
     Start,
     ZeroFound,
 }
@@ -23,12 +24,6 @@ impl PreambleParser {
     }
 }
 
-impl Default for PreambleParser {
-    fn default() -> Self {
-        PreambleParser::Start
-    }
-}
-
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 enum ResponseParser {
     Preamble(PreambleParser),
@@ -36,28 +31,37 @@ enum ResponseParser {
     LengthChksum(u8),
     FrameIdentifier(u8),
     Done(u8),
+    Ack,
+    Nack,
+    ErrorFrame,
 }
 
 impl ResponseParser {
-    pub fn next(&mut self, b: u8) -> Result<bool, DataError> {
+    // `tfi` is the expected frame identifier, 0xD5 for PN532.
+    pub fn next(&mut self, b: u8, tfi: u8) -> Result<bool, DataError> {
         use self::ResponseParser::*;
 
         *self = match *self {
             Preamble(pp)                              => pp.next(b)
                                                            .map_or(Length, Preamble),
             Length                                    => LengthChksum(b),
+            LengthChksum(0x00) if b == 0xFF           => Ack,
+            LengthChksum(0xFF) if b == 0x00           => Nack,
             LengthChksum(l) if l.wrapping_add(b) == 0 => FrameIdentifier(l),
-            FrameIdentifier(l) if b == 0xD5           => Done(l),
+            FrameIdentifier(1) if b == 0x7F           => ErrorFrame,
+            FrameIdentifier(l) if b == tfi            => Done(l),
             Done(l)                                   => Done(l),
+            Ack                                       => Ack,
+            Nack                                      => Nack,
+            ErrorFrame                                => ErrorFrame,
 
-            LengthChksum(_)    => return Err(DataError::InvalidChecksum(ChecksumType::Length)),
-            FrameIdentifier(_) => return Err(DataError::InvalidByte(b, "0xD5")),
+            LengthChksum(l)    => return Err(DataError::InvalidChecksum { which: ChecksumType::Length, received: b, expected: 0u8.wrapping_sub(l) }),
+            FrameIdentifier(_) => return Err(DataError::InvalidByte(b, "frame identifier")),
         };
 
-        if let Done(_) = *self {
-            Ok(false)
-        } else {
-            Ok(true)
+        match *self {
+            Done(_) | Ack | Nack | ErrorFrame => Ok(false),
+            _ => Ok(true),
         }
     }
 
@@ -76,58 +80,398 @@ impl Default for ResponseParser {
     }
 }
 
+/// Kind of frame received from PN532.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Frame {
+    /// Command was accepted.
+    Ack,
+    /// PN532 asks to resend the last frame.
+    Nack,
+    /// PN532 detected an error at application level (e.g. invalid command syntax).
+    ErrorFrame,
+    /// Normal information frame with given number of bytes of data copied.
+    Data(usize),
+}
+
+impl Frame {
+    /// Returns length of data if this is information frame.
+    pub fn data_len(self) -> Option<usize> {
+        if let Frame::Data(len) = self {
+            Some(len)
+        } else {
+            None
+        }
+    }
+
+    // Error frame replaces the reply, so it's reported as such rather than as unexpected frame.
+    fn expect_data<E: ::core::error::Error>(self) -> Result<usize, RecvError<E>> {
+        match self {
+            Frame::Data(len) => Ok(len),
+            Frame::ErrorFrame => Err(RecvError::ErrorFrame),
+            _ => Err(RecvError::UnexpectedFrame(self)),
+        }
+    }
+
+    fn expect_ack<E: ::core::error::Error>(self) -> Result<(), RecvError<E>> {
+        if self == Frame::Ack {
+            Ok(())
+        } else {
+            Err(RecvError::UnexpectedFrame(self))
+        }
+    }
+}
+
+/// Response code and length of data of a reply to command.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ResponseHeader {
+    /// Response code, always the command code plus one.
+    pub response_code: u8,
+    /// Number of bytes following the response code.
+    pub payload_len: usize,
+}
+
+impl ResponseHeader {
+    /// Checks that `reply` (data of information frame) is a reply to command `command_code`.
+    pub fn parse<E: ::core::error::Error>(reply: &[u8], command_code: u8) -> Result<Self, RecvError<E>> {
+        let response_code = *reply.first().ok_or(RecvError::UnexpectedEnd)?;
+        if response_code != command_code.wrapping_add(1) {
+            return Err(RecvError::InvalidData(DataError::InvalidByte(response_code, "response code")));
+        }
+        Ok(ResponseHeader {
+            response_code,
+            payload_len: reply.len() - 1,
+        })
+    }
+}
+
+// Normal information frame is laid out as
+// 00 (preamble), 00 FF (start code), LEN, LCS, D4 (TFI), data, DCS, 00 (postamble).
+// Number of bytes in normal information frame other than data.
+const FRAME_OVERHEAD: usize = 8;
+
+/// Size of buffer able to hold any normal information frame.
+pub const FRAME_BUF_LEN: usize = 262;
+
+/// Offset of data in the buffer passed to `send_in_place`.
+pub const DATA_OFFSET: usize = 6;
+
+/// Maximum number of bytes sent before each frame, see `set_preamble`.
+pub const MAX_PREAMBLE_LEN: usize = 16;
+
+// Number of bytes read when waiting for ACK, leaving room for short response following it.
+const ACK_READ_LEN: usize = 32;
+
+/// Default maximum number of bytes scanned while looking for a frame, see `PN532Proto::set_max_scan_len`.
+pub const DEFAULT_MAX_SCAN_LEN: u16 = 512;
+
+// Maximum number of stale frames discarded by resync_with_timeout.
+const MAX_STALE_FRAMES: usize = 4;
+
+/// Counters of received frames which failed checksum verification.
+///
+/// Rising counts indicate noise on the bus or in RF field before it causes total failure.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProtoStats {
+    pub length_checksum_errors: u32,
+    pub data_checksum_errors: u32,
+}
+
+/// Direction of traced frame.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    /// Frame sent by host to PN532.
+    Sent,
+    /// Bytes read from PN532, including the ready status byte.
+    Received,
+}
+
+/// Callback invoked with every frame sent or received, together with the time it happened.
+#[cfg(feature = "std")]
+pub type TraceFn = Box<dyn FnMut(Direction, ::std::time::Instant, &[u8]) + Send>;
+
 pub struct PN532Proto<D: bus::WaitRead + bus::BusWrite> {
     device: D,
+    auto_resync: bool,
+    max_scan_len: u16,
+    preamble: [u8; MAX_PREAMBLE_LEN],
+    preamble_len: usize,
+    host_tfi: u8,
+    device_tfi: u8,
+    stats: ProtoStats,
+    // Frame which was read together with ACK, prefixed by ready status byte.
+    pending: [u8; ACK_READ_LEN],
+    // Kept small, `PN532` is returned inside errors.
+    pending_len: u8,
+    #[cfg(feature = "std")]
+    trace: Option<TraceFn>,
 }
 
 impl<D: bus::WaitRead + bus::BusWrite> PN532Proto<D> {
     pub fn new(device: D) -> Self {
-        PN532Proto { device: device }
+        PN532Proto {
+            device,
+            auto_resync: false,
+            max_scan_len: DEFAULT_MAX_SCAN_LEN,
+            preamble: [0; MAX_PREAMBLE_LEN],
+            preamble_len: 0,
+            host_tfi: 0xD4,
+            device_tfi: 0xD5,
+            stats: ProtoStats::default(),
+            pending: [0; ACK_READ_LEN],
+            pending_len: 0,
+            #[cfg(feature = "std")]
+            trace: None,
+        }
+    }
+
+    /// Sets callback invoked with raw bytes of every frame sent or received.
+    ///
+    /// Sent frames are traced without the extra preamble set by `set_preamble`.
+    /// Useful for protocol analyzers and capturing the communication.
+    #[cfg(feature = "std")]
+    pub fn set_trace(&mut self, trace: TraceFn) {
+        self.trace = Some(trace);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn clear_trace(&mut self) {
+        self.trace = None;
+    }
+
+    #[cfg(feature = "std")]
+    fn trace(&mut self, direction: Direction, bytes: &[u8]) {
+        if let Some(ref mut trace) = self.trace {
+            trace(direction, ::std::time::Instant::now(), bytes);
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn trace(&mut self, _: Direction, _: &[u8]) {}
+
+    // Traces sent frame written in multiple parts, copying them only if tracing is enabled.
+    #[cfg(feature = "std")]
+    fn trace_sent(&mut self, parts: &[&[u8]]) {
+        if self.trace.is_some() {
+            let mut frame = [0u8; FRAME_BUF_LEN];
+            let mut len = 0;
+            for part in parts {
+                frame[len..(len + part.len())].copy_from_slice(part);
+                len += part.len();
+            }
+            self.trace(Direction::Sent, &frame[..len]);
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn trace_sent(&mut self, _: &[&[u8]]) {}
+
+    /// Returns counters of errors in received frames.
+    pub fn stats(&self) -> ProtoStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = ProtoStats::default();
+    }
+
+    /// Sets frame identifiers (direction bytes) of information frames.
+    ///
+    /// `host_tfi` is sent in frames from host, `device_tfi` is expected in frames from the chip.
+    /// The defaults are `0xD4` and `0xD5`, as used by PN532 and PN533.
+    pub fn set_direction_bytes(&mut self, host_tfi: u8, device_tfi: u8) {
+        self.host_tfi = host_tfi;
+        self.device_tfi = device_tfi;
+    }
+
+    /// Sets bytes written before every frame, including ACK.
+    ///
+    /// These are sent in addition to the regular preamble byte of the frame.
+    /// HSU needs `0x55` and a few zeroes to wake PN532 up, some buses need extra zeroes
+    /// to settle before the start code. No extra bytes are sent by default.
+    pub fn set_preamble(&mut self, bytes: &[u8]) -> Result<(), SendError<D::WriteError>> {
+        if bytes.len() > MAX_PREAMBLE_LEN {
+            return Err(SendError::TooMuchData(bytes.len()));
+        }
+
+        self.preamble[..bytes.len()].copy_from_slice(bytes);
+        self.preamble_len = bytes.len();
+        Ok(())
+    }
+
+    /// Wakes PN532 up in the way appropriate for the bus, see `BusWrite::wakeup`.
+    pub fn wakeup(&mut self) -> Result<(), SendError<D::WriteError>> {
+        Ok(self.device.wakeup()?)
+    }
+
+    /// Kind of the bus PN532 is connected through, see `BusWrite::kind`.
+    pub fn bus_kind(&self) -> bus::BusKind {
+        self.device.kind()
+    }
+
+    /// If enabled, `resync_with_timeout` is called automatically when receiving times out.
+    ///
+    /// Stale frames are awaited for the same time as the receiving which timed out.
+    pub fn set_auto_resync(&mut self, enabled: bool) {
+        self.auto_resync = enabled;
+    }
+
+    /// Sets maximum number of bytes scanned while receiving single frame.
+    ///
+    /// Junk preceding the frame and reads reporting not ready status count towards the limit.
+    /// Once it's exceeded, receiving fails with `RecvError::ScanLimitExceeded`, so a malfunctioning
+    /// device streaming bytes which never form a frame can't keep `recv` busy.
+    /// The default is `DEFAULT_MAX_SCAN_LEN`.
+    pub fn set_max_scan_len(&mut self, len: u16) {
+        self.max_scan_len = len;
+    }
+
+    /// Restores known state after interrupted or failed exchange.
+    ///
+    /// Sends ACK frame, which makes PN532 abort the command it's processing and drop
+    /// the response it didn't send yet, so the next `recv` won't see stale data.
+    pub fn resync(&mut self) -> Result<(), SendError<D::WriteError>> {
+        self.send_ack()
+    }
+
+    #[cfg(test)]
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Maximum length of data in single frame supported by both PN532 and the bus.
+    pub fn max_data_len(&self) -> usize {
+        use ::core::cmp::min;
+
+        min(254, self.device.max_write_len().saturating_sub(FRAME_OVERHEAD + self.preamble_len))
     }
 
     pub fn send(&mut self, data: &[u8]) -> Result<(), SendError<D::WriteError>> {
         if data.len() > 254 {
             return Err(SendError::TooMuchData(data.len()));
         }
-        let mut outbuf = [0u8; 262];
-         
-        outbuf[1] = 0xFF;
-        outbuf[2] = (data.len() + 1) as u8;
-        outbuf[3] = 0u8.wrapping_sub(outbuf[2]);
-        outbuf[4] = 0xD4;
-        outbuf[5+data.len()] = 0u8.wrapping_sub(calc_checksum(0xD4, data));
-        outbuf[5..(5 + data.len())].copy_from_slice(data);
 
-        self.device.write(&outbuf[0..(data.len() + 6)]).map_err(Into::into)
+        self.pending_len = 0;
+        let len = (data.len() + 1) as u8;
+        let header = [0x00, 0x00, 0xFF, len, 0u8.wrapping_sub(len), self.host_tfi];
+        let trailer = [0u8.wrapping_sub(calc_checksum(self.host_tfi, data)), 0x00];
+
+        self.device.write_vectored(&[&self.preamble[..self.preamble_len], &header, data, &trailer])?;
+        self.trace_sent(&[&header, data, &trailer]);
+        Ok(())
+    }
+
+    /// Sends `len` bytes of data already stored in `frame` at `DATA_OFFSET`.
+    /// The rest of `frame` is overwritten by framing, so no other buffer is needed.
+    pub fn send_in_place(&mut self, frame: &mut [u8; FRAME_BUF_LEN], len: usize) -> Result<(), SendError<D::WriteError>> {
+        if len > 254 {
+            return Err(SendError::TooMuchData(len));
+        }
+
+        self.pending_len = 0;
+        frame[0] = 0x00;
+        frame[1] = 0x00;
+        frame[2] = 0xFF;
+        frame[3] = (len + 1) as u8;
+        frame[4] = 0u8.wrapping_sub(frame[3]);
+        frame[5] = self.host_tfi;
+        frame[DATA_OFFSET + len] = 0u8.wrapping_sub(calc_checksum(self.host_tfi, &frame[DATA_OFFSET..(DATA_OFFSET + len)]));
+        frame[DATA_OFFSET + len + 1] = 0x00;
+
+        if self.preamble_len == 0 {
+            self.device.write(&frame[0..(len + FRAME_OVERHEAD)])?;
+        } else {
+            self.device.write_vectored(&[&self.preamble[..self.preamble_len], &frame[0..(len + FRAME_OVERHEAD)]])?;
+        }
+        self.trace(Direction::Sent, &frame[0..(len + FRAME_OVERHEAD)]);
+        Ok(())
     }
 
     pub fn send_ack(&mut self) -> Result<(), SendError<D::WriteError>> {
-        let buf = [0x00, 0xFF, 0x00, 0xFF];
-        self.device.write(&buf).map_err(Into::into)
+        let buf = [0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+        self.device.write_vectored(&[&self.preamble[..self.preamble_len], &buf])?;
+        self.trace(Direction::Sent, &buf);
+        Ok(())
     }
 
     pub fn send_wait_ack(&mut self, data: &[u8]) -> CommResult<(), D::ReadError, D::WriteError> {
-        try!(self.send(data));
-        try!(self.recv_ack());
+        self.send(data)?;
+        self.recv_ack()?;
         Ok(())
     }
 
-    fn process_packet(recved: &[u8], dst: &mut [u8]) -> Result<usize, RecvError<D::ReadError>> {
-        use ::std::cmp::min;
+    /// Same as `send_wait_ack` but without copying the data, see `send_in_place`.
+    pub fn send_wait_ack_in_place(&mut self, frame: &mut [u8; FRAME_BUF_LEN], len: usize) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.send_in_place(frame, len)?;
+        self.recv_ack()?;
+        Ok(())
+    }
+
+    fn process_packet(recved: &[u8], dst: &mut [u8], tfi: u8, max_scan_len: usize, stats: &mut ProtoStats) -> Result<Frame, RecvError<D::ReadError>> {
+        use ::core::cmp::min;
+
+        let (frame, start) = Self::locate_packet(recved, tfi, max_scan_len, stats)?;
+        let len = match frame {
+            Frame::Data(len) => len,
+            other => return Ok(other),
+        };
+
+        let to_copy = min(len, dst.len());
+
+        dst[0..to_copy].copy_from_slice(&recved[start..(start + to_copy)]);
+
+        if to_copy < len {
+            return Err(RecvError::Truncated { copied: to_copy, total: len });
+        }
+
+        Ok(Frame::Data(to_copy))
+    }
+
+    // Same as `process_packet`, but moves the data to the start of `buf`.
+    fn process_packet_in_place(buf: &mut [u8], tfi: u8, max_scan_len: usize, stats: &mut ProtoStats) -> Result<Frame, RecvError<D::ReadError>> {
+        let (frame, start) = Self::locate_packet(buf, tfi, max_scan_len, stats)?;
+        if let Frame::Data(len) = frame {
+            buf.copy_within(start..(start + len), 0);
+        }
+        Ok(frame)
+    }
+
+    // Parses the frame, returning it together with the offset of its data within `recved`.
+    fn locate_packet(recved: &[u8], tfi: u8, max_scan_len: usize, stats: &mut ProtoStats) -> Result<(Frame, usize), RecvError<D::ReadError>> {
+        if recved.is_empty() {
+            return Err(RecvError::NoData);
+        }
 
         let mut iter = recved.iter();
         let mut parser = ResponseParser::default();
-        for b in iter.by_ref() {
-            if !try!(parser.next(*b)) {
-                break;
+        for (i, b) in iter.by_ref().enumerate() {
+            if i >= max_scan_len {
+                return Err(RecvError::ScanLimitExceeded(max_scan_len));
+            }
+            match parser.next(*b, tfi) {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) => {
+                    if let DataError::InvalidChecksum { which: ChecksumType::Length, .. } = e {
+                        stats.length_checksum_errors = stats.length_checksum_errors.saturating_add(1);
+                    }
+                    return Err(e.into());
+                }
             }
         }
 
-        let len = try!(parser.pkt_len().ok_or(RecvError::UnexpectedEnd)) as usize;
+        let len = match parser {
+            ResponseParser::Ack => return Ok((Frame::Ack, 0)),
+            ResponseParser::Nack => return Ok((Frame::Nack, 0)),
+            ResponseParser::ErrorFrame => return Ok((Frame::ErrorFrame, 0)),
+            _ => parser.pkt_len().ok_or(RecvError::UnexpectedEnd)? as usize,
+        };
 
+        // Checked before looking at the data, corrupt length must not make us trust garbage.
         let pkt = iter.as_slice();
         if len > pkt.len() {
-            return Err(RecvError::UnexpectedEnd);
+            return Err(RecvError::InvalidData(DataError::LengthOutOfRange { len: len as u8, max: pkt.len() }));
         }
 
         if len == 0 {
@@ -135,55 +479,205 @@ impl<D: bus::WaitRead + bus::BusWrite> PN532Proto<D> {
         }
 
         let slice = &pkt[0..len];
-        if calc_checksum(0xD5, &slice) != 0 {
-            return Err(RecvError::InvalidData(DataError::InvalidChecksum(ChecksumType::Data)));
+        if calc_checksum(tfi, slice) != 0 {
+            stats.data_checksum_errors = stats.data_checksum_errors.saturating_add(1);
+            let received = slice[len - 1];
+            let expected = 0u8.wrapping_sub(calc_checksum(tfi, &slice[..(len - 1)]));
+            return Err(RecvError::InvalidData(DataError::InvalidChecksum { which: ChecksumType::Data, received, expected }));
         }
 
-        let to_copy = min(len - 1, dst.len());
+        Ok((Frame::Data(len - 1), recved.len() - pkt.len()))
+    }
+
+    /// Receives any kind of frame, copying data of information frame into `data`.
+    pub fn recv_frame(&mut self, data: &mut[u8]) -> Result<Frame, RecvError<D::ReadError>> {
+        let mut buf = [0u8; FRAME_BUF_LEN + 1];
+        self.recv_frame_into(&mut buf, data)
+    }
 
-        let slice = &slice[0..to_copy];
-        let dst = &mut dst[0..to_copy];
+    /// Same as `recv_frame`, but reads the raw frame into `scratch` instead of a stack buffer.
+    ///
+    /// `scratch` must be able to hold the ready status byte and the whole frame,
+    /// `FRAME_BUF_LEN + 1` bytes are enough for any frame.
+    pub fn recv_frame_into(&mut self, scratch: &mut [u8], data: &mut[u8]) -> Result<Frame, RecvError<D::ReadError>> {
+        if let Some(frame) = self.take_pending(data) {
+            return frame;
+        }
 
-        dst.copy_from_slice(slice);
+        let len = self.device.wait_read(scratch).map_err(RecvError::ReadError)?;
+        self.trace(Direction::Received, &scratch[0..len]);
 
-        Ok(to_copy)
+        Self::process_packet(&scratch[0..len], data, self.device_tfi, self.max_scan_len as usize, &mut self.stats)
     }
 
     pub fn recv(&mut self, data: &mut[u8]) -> Result<usize, RecvError<D::ReadError>> {
-        let mut buf = [0u8; 32];
-        let len = try!(self.device.wait_read(&mut buf).map_err(RecvError::ReadError));
+        self.recv_frame(data)?.expect_data()
+    }
+
+    /// Receives information frame into `buf`, moving its data to the start of it.
+    ///
+    /// Avoids the second buffer of `recv_into`, `buf` is subject to the same requirements as its `scratch`.
+    pub fn recv_in_place(&mut self, buf: &mut [u8]) -> Result<usize, RecvError<D::ReadError>> {
+        if let Some(frame) = self.take_pending(buf) {
+            return frame?.expect_data();
+        }
+
+        let len = self.device.wait_read(buf).map_err(RecvError::ReadError)?;
+        self.trace(Direction::Received, &buf[0..len]);
 
-        Self::process_packet(&buf[0..len], data)
+        Self::process_packet_in_place(&mut buf[0..len], self.device_tfi, self.max_scan_len as usize, &mut self.stats)?.expect_data()
     }
 
-    pub fn recv_ack(&mut self) -> Result<(), RecvError<D::ReadError>> {
-        let mut buf = [0u8; 32];
-        try!(self.device.wait_read(&mut buf).map_err(RecvError::ReadError));
+    /// Same as `recv`, but reads the raw frame into `scratch`, see `recv_frame_into`.
+    pub fn recv_into(&mut self, scratch: &mut [u8], data: &mut[u8]) -> Result<usize, RecvError<D::ReadError>> {
+        self.recv_frame_into(scratch, data)?.expect_data()
+    }
 
-        let mut parser = PreambleParser::default();
-        for b in &buf {
-            parser = match parser.next(*b) {
-                Some(parser) => parser,
-                None => return Ok(()),
-            };
+    /// Receives ACK frame and returns the number of bytes preceding its start code
+    /// (not counting the ready status byte).
+    ///
+    /// Well-formed frame is preceded only by the preamble, so more bytes mean the bus prepends junk.
+    ///
+    /// If the response to the command follows the ACK in the same read, it's kept
+    /// and returned by the next `recv`.
+    pub fn recv_ack(&mut self) -> Result<usize, RecvError<D::ReadError>> {
+        let mut buf = [0u8; ACK_READ_LEN];
+        // Some boards report not-ready status on the first read after a command,
+        // `wait_read` returns only once the bus reports ready, however it finds that out.
+        let len = self.device.wait_read(&mut buf).map_err(RecvError::ReadError)?;
+        self.trace(Direction::Received, &buf[0..len]);
+
+        Self::process_packet(&buf[0..len], &mut [], self.device_tfi, self.max_scan_len as usize, &mut self.stats)?.expect_ack()?;
+        // The start code was found by process_packet.
+        let preceding = buf[1..len].windows(2).position(|w| w == [0x00, 0xFF]).unwrap_or(0);
+
+        // Start code is followed by 00 FF and postamble.
+        let ack_end = 1 + preceding + 5;
+        // The postamble may be missing in short read.
+        let rest = buf.get(ack_end..len).unwrap_or(&[]);
+        if rest.windows(2).any(|w| w == [0x00, 0xFF]) {
+            self.pending[0] = 0x01;
+            self.pending[1..(1 + rest.len())].copy_from_slice(rest);
+            self.pending_len = (1 + rest.len()) as u8;
         }
 
-        Err(RecvError::UnexpectedEnd)
+        Ok(preceding)
     }
 
+    // Returns frame read together with ACK, if it was complete.
+    // Anything else is ignored, since the bytes following ACK may just be junk.
+    // Truncation is reported though, the frame itself was valid.
+    fn take_pending(&mut self, data: &mut [u8]) -> Option<Result<Frame, RecvError<D::ReadError>>> {
+        if self.pending_len == 0 {
+            return None;
+        }
+
+        let len = self.pending_len as usize;
+        self.pending_len = 0;
+        let mut stats = self.stats;
+        let frame = match Self::process_packet(&self.pending[..len], data, self.device_tfi, self.max_scan_len as usize, &mut stats) {
+            Ok(frame) => Ok(frame),
+            Err(e @ RecvError::Truncated { .. }) => Err(e),
+            Err(_) => return None,
+        };
+        self.stats = stats;
+        Some(frame)
+    }
+
+    /// Receives reply to a command and acknowledges it by sending ACK frame.
+    ///
+    /// ACK of the command itself must be already consumed (`send_wait_ack` does it),
+    /// receiving it here fails with `RecvError::UnexpectedFrame(Frame::Ack)`.
+    /// Returns the length of data copied into `data`, starting with the response code.
+    ///
+    /// If PN532 replies with error frame, `RecvError::ErrorFrame` is returned and ACK isn't sent.
     pub fn recv_reply_ack(&mut self, data: &mut[u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
-        let len = try!(self.recv(data));
-        try!(self.send_ack());
+        let len = self.recv(data)?;
+        self.send_ack()?;
+        Ok(len)
+    }
+
+    /// Same as `recv_reply_ack`, but reads the raw frame into `scratch`, see `recv_frame_into`.
+    pub fn recv_reply_ack_into(&mut self, scratch: &mut [u8], data: &mut[u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let len = self.recv_into(scratch, data)?;
+        self.send_ack()?;
+        Ok(len)
+    }
+
+    /// Same as `recv_reply_ack`, but receives the reply in place, see `recv_in_place`.
+    pub fn recv_reply_ack_in_place(&mut self, buf: &mut [u8]) -> CommResult<usize, D::ReadError, D::WriteError> {
+        let len = self.recv_in_place(buf)?;
+        self.send_ack()?;
         Ok(len)
     }
 }
 
-impl<D: bus::WaitRead + bus::WaitReadTimeout + bus::BusWrite> PN532Proto<D> {
-    pub fn recv_with_timeout(&mut self, data: &mut[u8], timeout: D::Duration) -> WaitResult<usize, RecvError<D::ReadError>> {
-        let mut buf = [0u8; 32];
-        let len = try!(self.device.wait_read_timeout(&mut buf, timeout).map_err(|e| e.map(RecvError::ReadError)));
+impl<D: bus::WaitRead + bus::WaitReadTimeout + bus::BusWrite> PN532Proto<D> where D::Duration: Clone {
+    pub fn recv_with_timeout(&mut self, data: &mut[u8], timeout: D::Duration) -> WaitResult<usize, RecvError<D::ReadError>, D::Duration> {
+        let mut buf = [0u8; FRAME_BUF_LEN + 1];
+        self.recv_with_timeout_into(&mut buf, data, timeout)
+    }
+
+    /// Same as `recv_with_timeout`, but reads the raw frame into `scratch`, see `recv_frame_into`.
+    pub fn recv_with_timeout_into(&mut self, scratch: &mut [u8], data: &mut[u8], timeout: D::Duration) -> WaitResult<usize, RecvError<D::ReadError>, D::Duration> {
+        if let Some(frame) = self.take_pending(data) {
+            return frame.and_then(Frame::expect_data).map_err(Into::into);
+        }
+
+        let res = self.device.wait_read_timeout(scratch, timeout);
+        self.finish_wait_recv(res, scratch, data)
+    }
+
+    /// Same as `recv_with_timeout_into`, but also returns `Err(WaitError::Cancelled)` once `cancel` is cancelled.
+    #[cfg(feature = "tags")]
+    pub fn recv_cancellable_into(&mut self, scratch: &mut [u8], data: &mut[u8], timeout: D::Duration, cancel: &bus::CancelToken) -> WaitResult<usize, RecvError<D::ReadError>, D::Duration> {
+        if let Some(frame) = self.take_pending(data) {
+            return frame.and_then(Frame::expect_data).map_err(Into::into);
+        }
+
+        let res = self.device.wait_read_cancellable(scratch, timeout, cancel);
+        self.finish_wait_recv(res, scratch, data)
+    }
+
+    // Parses the frame read by timed wait into `scratch`.
+    fn finish_wait_recv(&mut self, res: WaitResult<usize, D::ReadError, D::Duration>, scratch: &[u8], data: &mut[u8]) -> WaitResult<usize, RecvError<D::ReadError>, D::Duration> {
+        let len = match res {
+            Ok(len) => len,
+            Err(WaitError::Timeout(elapsed)) => {
+                // The response may still arrive later and confuse the next recv.
+                // Failing to resync doesn't change the fact that the operation timed out.
+                if self.auto_resync {
+                    let _ = self.resync_with_timeout(elapsed.clone());
+                }
+                return Err(WaitError::Timeout(elapsed));
+            },
+            Err(WaitError::Cancelled) => return Err(WaitError::Cancelled),
+            Err(WaitError::OtherError(e)) => return Err(WaitError::OtherError(RecvError::ReadError(e))),
+        };
+        self.trace(Direction::Received, &scratch[0..len]);
+
+        Self::process_packet(&scratch[0..len], data, self.device_tfi, self.max_scan_len as usize, &mut self.stats)
+            .and_then(Frame::expect_data)
+            .map_err(Into::into)
+    }
+
+    /// Same as `resync`, but also discards frames PN532 sends until it stays quiet for `quiet` time.
+    ///
+    /// Fails with `RecvError::StillStreaming` if PN532 doesn't go quiet after few frames.
+    pub fn resync_with_timeout(&mut self, quiet: D::Duration) -> WaitResult<(), CommError<D::ReadError, D::WriteError>, D::Duration> {
+        self.resync().map_err(|e| WaitError::OtherError(CommError::SendError(e)))?;
+
+        let mut buf = [0u8; FRAME_BUF_LEN + 1];
+        for _ in 0..MAX_STALE_FRAMES {
+            match self.device.wait_read_timeout(&mut buf, quiet.clone()) {
+                Ok(len) => self.trace(Direction::Received, &buf[0..len]),
+                Err(WaitError::Timeout(_)) => return Ok(()),
+                Err(WaitError::Cancelled) => return Err(WaitError::Cancelled),
+                Err(WaitError::OtherError(e)) => return Err(WaitError::OtherError(RecvError::ReadError(e).into())),
+            }
+        }
 
-        Self::process_packet(&buf[0..len], data).map_err(Into::into)
+        Err(WaitError::OtherError(RecvError::StillStreaming(MAX_STALE_FRAMES).into()))
     }
 }
 
@@ -191,10 +685,10 @@ fn calc_checksum(init: u8, data: &[u8]) -> u8 {
     data.iter().fold(init, |a, b| a.wrapping_add(*b))
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use ::std::io;
-    use ::bus::{BusRead, BusWrite};
+    use ::bus::{BusRead, BusWrite, WaitRead};
 
     #[test]
     fn preamble_parser() {
@@ -229,14 +723,14 @@ mod test {
         let arr1 = [0, 1, 2, 0, 0xFF, 1, 0xFF, 0xD5];
         let mut iter = arr1.iter();
 
-        while parser.next(*iter.next().unwrap()).unwrap() {}
+        while parser.next(*iter.next().unwrap(), 0xD5).unwrap() {}
 
         assert_eq!(parser.pkt_len(), Some(1));
 
         let mut parser = ResponseParser::default();
         let arr2 = [0, 1, 2, 0, 0xFF, 1];
         for b in &arr2 {
-            assert_eq!(parser.next(*b), Ok(true));
+            assert_eq!(parser.next(*b, 0xD5), Ok(true));
         }
         assert_eq!(parser.pkt_len(), None);
     }
@@ -259,7 +753,7 @@ mod test {
         fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
             use ::std::cmp::min;
 
-            if buf.len() == 0 {
+            if buf.is_empty() {
                 Ok(0)
             } else {
                 let to_copy = min(buf.len(), self.buf_to_send.len());
@@ -295,7 +789,7 @@ mod test {
         fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
             use ::std::cmp::min;
 
-            if buf.len() == 0 {
+            if buf.is_empty() {
                 Ok(0)
             } else {
                 buf[0] = 0x01;
@@ -314,13 +808,329 @@ mod test {
 
             let to_copy = min(buf.len(), self.buf.len());
             self.buf[..to_copy].copy_from_slice(&buf[..to_copy]);
-            self.buf[4] = 0xD5;
-            self.buf[buf.len() - 1] = self.buf[buf.len() - 1].wrapping_sub(1);
+            self.buf[5] = 0xD5;
+            self.buf[buf.len() - 2] = self.buf[buf.len() - 2].wrapping_sub(1);
             
             Ok(())
         }
     }
 
+    // Echo is always ready, so it can be used without BusyWait.
+    impl WaitRead for Echo {
+        type ReadError = io::Error;
+
+        fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.read(buf)
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn frame_round_trip(data in ::proptest::collection::vec(::proptest::prelude::any::<u8>(), 0..255)) {
+            use super::PN532Proto;
+
+            let mut proto = PN532Proto::new(Echo::new());
+            proto.send(&data).unwrap();
+
+            let mut recvbuf = [0u8; 254];
+            let len = PN532Proto::<Echo>::process_packet(&proto.device.buf, &mut recvbuf, 0xD5, super::DEFAULT_MAX_SCAN_LEN as usize, &mut Default::default()).unwrap().data_len().unwrap();
+            prop_assert_eq!(&recvbuf[..len], &data[..]);
+        }
+    }
+
+    // Times out on every read, but may return some stale frames first.
+    struct Silent {
+        stale_frames: usize,
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl WaitRead for Silent {
+        type ReadError = io::Error;
+
+        fn wait_read(&mut self, _: &mut [u8]) -> Result<usize, io::Error> {
+            panic!("wait_read_timeout should be used");
+        }
+    }
+
+    impl ::bus::WaitReadTimeout for Silent {
+        type Duration = ::std::time::Duration;
+
+        fn wait_read_timeout(&mut self, buf: &mut [u8], timeout: Self::Duration) -> ::error::WaitResult<usize, io::Error> {
+            if self.stale_frames > 0 {
+                self.stale_frames -= 1;
+                buf[0] = 0x01;
+                Ok(buf.len())
+            } else {
+                Err(::error::WaitError::Timeout(timeout))
+            }
+        }
+    }
+
+    impl BusWrite for Silent {
+        type WriteError = io::Error;
+
+        fn write(&mut self, buf: &[u8]) -> Result<(), io::Error> {
+            self.writes.push(buf.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn auto_resync() {
+        use super::PN532Proto;
+        use ::std::time::Duration;
+
+        let mut proto = PN532Proto::new(Silent { stale_frames: 0, writes: Vec::new() });
+        let mut buf = [0u8; 8];
+        assert_matches!(proto.recv_with_timeout(&mut buf, Duration::from_millis(10)), Err(::error::WaitError::Timeout(_)));
+        assert!(proto.device.writes.is_empty());
+
+        proto.set_auto_resync(true);
+        assert_matches!(proto.recv_with_timeout(&mut buf, Duration::from_millis(10)), Err(::error::WaitError::Timeout(_)));
+        assert_eq!(proto.device.writes, vec![vec![0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]]);
+    }
+
+    #[test]
+    fn resync_with_timeout() {
+        use super::{PN532Proto, MAX_STALE_FRAMES};
+        use ::bus::{BusyWait, CancelToken};
+        use ::error::{CommError, RecvError, WaitError};
+        use ::std::time::Duration;
+
+        let mut proto = PN532Proto::new(Silent { stale_frames: 2, writes: Vec::new() });
+        proto.resync_with_timeout(Duration::from_millis(10)).unwrap();
+        assert_eq!(proto.device.stale_frames, 0);
+
+        let mut proto = PN532Proto::new(Silent { stale_frames: MAX_STALE_FRAMES, writes: Vec::new() });
+        assert_matches!(proto.resync_with_timeout(Duration::from_millis(10)).unwrap_err(), WaitError::OtherError(CommError::RecvError(RecvError::StillStreaming(MAX_STALE_FRAMES))));
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut proto = PN532Proto::new(BusyWait::with_cancel(BufSender { buf_to_send: &[0x00] }, Duration::from_millis(0), cancel));
+        assert_matches!(proto.resync_with_timeout(Duration::from_millis(10)).unwrap_err(), WaitError::Cancelled);
+    }
+
+    // Records parts of vectored writes.
+    struct Vectored {
+        parts: Vec<Vec<u8>>,
+    }
+
+    impl BusWrite for Vectored {
+        type WriteError = io::Error;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), io::Error> {
+            panic!("write_vectored should be used");
+        }
+
+        fn write_vectored(&mut self, bufs: &[&[u8]]) -> Result<(), io::Error> {
+            self.parts.extend(bufs.iter().map(|buf| buf.to_vec()));
+            Ok(())
+        }
+    }
+
+    impl WaitRead for Vectored {
+        type ReadError = io::Error;
+
+        fn wait_read(&mut self, _: &mut [u8]) -> Result<usize, io::Error> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn send_vectored() {
+        use super::PN532Proto;
+
+        let data = [0x4A, 0x01, 0x00];
+        let mut proto = PN532Proto::new(Vectored { parts: Vec::new() });
+        proto.send(&data).unwrap();
+        assert_eq!(proto.device.parts, vec![vec![], vec![0x00, 0x00, 0xFF, 0x04, 0xFC, 0xD4], data.to_vec(), vec![0xE1, 0x00]]);
+    }
+
+    #[test]
+    fn send_in_place_layout() {
+        use super::{PN532Proto, FRAME_BUF_LEN, DATA_OFFSET};
+
+        // Leftovers of previous frames must not leak into the framing.
+        let mut frame = [0xAAu8; FRAME_BUF_LEN];
+        frame[DATA_OFFSET..(DATA_OFFSET + 3)].copy_from_slice(&[0x4A, 0x01, 0x00]);
+        let mut proto = PN532Proto::new(Silent { stale_frames: 0, writes: Vec::new() });
+        proto.send_in_place(&mut frame, 3).unwrap();
+        assert_eq!(proto.device.writes, vec![vec![0x00, 0x00, 0xFF, 0x04, 0xFC, 0xD4, 0x4A, 0x01, 0x00, 0xE1, 0x00]]);
+    }
+
+    #[test]
+    fn send_preamble() {
+        use super::{PN532Proto, MAX_PREAMBLE_LEN};
+
+        let mut proto = PN532Proto::new(Vectored { parts: Vec::new() });
+        assert!(proto.set_preamble(&[0x00; MAX_PREAMBLE_LEN + 1]).is_err());
+        proto.set_preamble(&[0x55, 0x55, 0x00]).unwrap();
+        proto.send(&[0x02]).unwrap();
+        proto.send_ack().unwrap();
+        assert_eq!(proto.device.parts, vec![
+            vec![0x55, 0x55, 0x00], vec![0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD4], vec![0x02], vec![0x2A, 0x00],
+            vec![0x55, 0x55, 0x00], vec![0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00],
+        ]);
+    }
+
+    #[test]
+    fn trace() {
+        use super::{PN532Proto, Direction};
+        use ::std::sync::{Arc, Mutex};
+
+        let traced = Arc::new(Mutex::new(Vec::new()));
+        let mut proto = PN532Proto::new(Echo::new());
+        {
+            let traced = traced.clone();
+            proto.set_trace(Box::new(move |direction, _, bytes| traced.lock().unwrap().push((direction, bytes.to_vec()))));
+        }
+        proto.send(&[0x02]).unwrap();
+        let mut rcvbuf = [0u8; 8];
+        assert_eq!(proto.recv(&mut rcvbuf).unwrap(), 1);
+        proto.clear_trace();
+        proto.send_ack().unwrap();
+
+        let traced = traced.lock().unwrap();
+        assert_eq!(traced.len(), 2);
+        assert_eq!(traced[0], (Direction::Sent, vec![0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD4, 0x02, 0x2A, 0x00]));
+        assert_eq!(traced[1].0, Direction::Received);
+        assert_eq!(traced[1].1[..10], [0x01, 0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x02, 0x29, 0x00]);
+    }
+
+    #[test]
+    fn recv_into_long_frame() {
+        use super::{FRAME_BUF_LEN, calc_checksum};
+
+        let data = (0..200).map(|i| i as u8).collect::<Vec<_>>();
+        let mut frame = vec![0x01, 0x00, 0x00, 0xFF, 201, 0u8.wrapping_sub(201), 0xD5];
+        frame.extend_from_slice(&data);
+        frame.push(0u8.wrapping_sub(calc_checksum(0xD5, &data)));
+        frame.push(0x00);
+
+        let mut proto = b2p(&frame);
+        let mut scratch = [0u8; FRAME_BUF_LEN + 1];
+        let mut rcvbuf = [0u8; 254];
+        assert_eq!(proto.recv_into(&mut scratch, &mut rcvbuf).unwrap(), 200);
+        assert_eq!(rcvbuf[..200], data[..]);
+        assert_eq!(proto.recv(&mut rcvbuf).unwrap(), 200);
+        assert_eq!(rcvbuf[..200], data[..]);
+        assert_eq!(proto.recv_in_place(&mut scratch).unwrap(), 200);
+        assert_eq!(scratch[..200], data[..]);
+        assert_matches!(proto.recv_into(&mut [0u8; 32], &mut rcvbuf), Err(::error::RecvError::InvalidData(::error::DataError::LengthOutOfRange { len: 201, max: 25 })));
+    }
+
+    #[test]
+    fn recv_no_data() {
+        use super::PN532Proto;
+
+        let mut rcvbuf = [0u8; 8];
+        let res = PN532Proto::<Echo>::process_packet(&[], &mut rcvbuf, 0xD5, super::DEFAULT_MAX_SCAN_LEN as usize, &mut Default::default());
+        assert_matches!(res, Err(::error::RecvError::NoData));
+        let res = PN532Proto::<Echo>::process_packet(&[0x00, 0x00], &mut rcvbuf, 0xD5, super::DEFAULT_MAX_SCAN_LEN as usize, &mut Default::default());
+        assert_matches!(res, Err(::error::RecvError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn checksum_stats() {
+        let mut rcvbuf = [0u8; 32];
+        let mut proto = b2p(&[0x01, 0x00, 0xFF, 0x02, 0xFF, 0xD5]);
+        assert!(proto.recv(&mut rcvbuf).is_err());
+        assert!(proto.recv(&mut rcvbuf).is_err());
+        assert_eq!(proto.stats().length_checksum_errors, 2);
+
+        let mut proto = b2p(&[0x01, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x00, 0x00]);
+        assert!(proto.recv(&mut rcvbuf).is_err());
+        assert_eq!(proto.stats().data_checksum_errors, 1);
+        assert_eq!(proto.stats().length_checksum_errors, 0);
+        proto.reset_stats();
+        assert_eq!(proto.stats(), Default::default());
+    }
+
+    #[test]
+    fn direction_bytes() {
+        use ::bus::BusyWait;
+        use super::PN532Proto;
+
+        let mut proto = PN532Proto::new(Vectored { parts: Vec::new() });
+        proto.set_direction_bytes(0xD6, 0xD7);
+        proto.send(&[0x02]).unwrap();
+        assert_eq!(proto.device.parts, vec![vec![], vec![0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD6], vec![0x02], vec![0x28, 0x00]]);
+
+        let mut proto = PN532Proto::new(BusyWait::new(BufSender { buf_to_send: &[0x01, 0x00, 0xFF, 0x02, 0xFE, 0xD7, 0x03, 0x26] }));
+        proto.set_direction_bytes(0xD6, 0xD7);
+        let mut rcvbuf = [0u8; 4];
+        assert_eq!(proto.recv(&mut rcvbuf).unwrap(), 1);
+        assert_eq!(rcvbuf[0], 0x03);
+    }
+
+    // Returns prepared reads one by one, regardless of ready bit.
+    struct Scripted<'a> {
+        reads: &'a [&'a [u8]],
+        count: usize,
+    }
+
+    impl<'a> BusRead for Scripted<'a> {
+        type ReadError = io::Error;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            use ::std::cmp::min;
+
+            let read = self.reads[self.count];
+            self.count += 1;
+            let to_copy = min(buf.len(), read.len());
+            buf[..to_copy].copy_from_slice(&read[..to_copy]);
+            for b in &mut buf[to_copy..] {
+                *b = 0;
+            }
+            Ok(buf.len())
+        }
+    }
+
+    impl<'a> WaitRead for Scripted<'a> {
+        type ReadError = io::Error;
+
+        fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.read(buf)
+        }
+    }
+
+    impl<'a> BusWrite for Scripted<'a> {
+        type WriteError = io::Error;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    // Returns prepared read once, reporting only its length.
+    struct ShortRead<'a>(&'a [u8]);
+
+    impl<'a> WaitRead for ShortRead<'a> {
+        type ReadError = io::Error;
+
+        fn wait_read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            buf[..self.0.len()].copy_from_slice(self.0);
+            Ok(self.0.len())
+        }
+    }
+
+    impl<'a> BusWrite for ShortRead<'a> {
+        type WriteError = io::Error;
+
+        fn write(&mut self, _: &[u8]) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recv_ack_short_read() {
+        use super::PN532Proto;
+
+        let mut proto = PN532Proto::new(ShortRead(&[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF]));
+        assert_eq!(proto.recv_ack().unwrap(), 1);
+        assert_eq!(proto.pending_len, 0);
+    }
+
     // buf to proto
     fn b2p<'a>(buf: &'a [u8]) -> super::PN532Proto<::bus::BusyWait<BufSender<'a>>> {
         use super::PN532Proto;
@@ -353,8 +1163,16 @@ mod test {
                  |_, res| assert_matches!(res.unwrap_err(), RecvError::UnexpectedEnd));
         chk_recv!([0x01, 0x00],
                  |_, res| assert_matches!(res.unwrap_err(), RecvError::UnexpectedEnd));
-        chk_recv!([0x01, 0x00, 0xFF, 0xFF, 0x01, 0xD5],
-                 |_, res| assert_matches!(res.unwrap_err(), RecvError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn recv_length_out_of_range() {
+        use ::error::{RecvError, DataError};
+
+        // The read is 32 bytes long, so 26 bytes follow TFI.
+        let mut proto = b2p(&[0x01, 0x00, 0xFF, 0xFF, 0x01, 0xD5]);
+        let res = proto.recv_into(&mut [0u8; 32], &mut [0u8; 32]);
+        assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::LengthOutOfRange { len: 0xFF, max: 26 }));
     }
 
     #[test]
@@ -410,13 +1228,13 @@ mod test {
         use ::error::{RecvError, DataError, ChecksumType};
 
         chk_recv!([0x01, 0x00, 0xFF, 0x02, 0xFF, 0xD5],
-                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum(ChecksumType::Length))));
-        chk_recv!([0x01, 0x00, 0xFF, 0x00, 0xFF, 0xD5],
-                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum(ChecksumType::Length))));
+                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum { which: ChecksumType::Length, received: 0xFF, expected: 0xFE })));
+        chk_recv!([0x01, 0x00, 0xFF, 0x01, 0x00, 0xD5],
+                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum { which: ChecksumType::Length, received: 0x00, expected: 0xFF })));
         chk_recv!([0x01, 0x00, 0xFF, 0x01, 0xFF, 0xD5, 0x01],
-                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum(ChecksumType::Data))));
+                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum { which: ChecksumType::Data, received: 0x01, expected: 0x2B })));
         chk_recv!([0x01, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x00, 0x00],
-                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum(ChecksumType::Data))));
+                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidChecksum { which: ChecksumType::Data, received: 0x00, expected: 0x2B })));
     }
 
     #[test]
@@ -424,11 +1242,151 @@ mod test {
         use ::error::{RecvError, DataError};
 
         chk_recv!([0x01, 0x00, 0xFF, 0x01, 0xFF, 0xD4],
-                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidByte(0xD4, "0xD5"))));
+                 |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidByte(0xD4, "frame identifier"))));
         chk_recv!([0x01, 0x00, 0xFF, 0x00, 0x00, 0xD5],
                  |_, res| assert_matches!(res.unwrap_err(), RecvError::InvalidData(DataError::InvalidByte(0x00, "value at least 0x01"))));
     }
 
+    #[test]
+    fn recv_special_frames() {
+        use ::error::RecvError;
+        use super::Frame;
+
+        chk_recv!([0x01, 0x00, 0xFF, 0x00, 0xFF, 0xD5],
+                 |_, res| assert_matches!(res.unwrap_err(), RecvError::UnexpectedFrame(Frame::Ack)));
+
+        let mut rcvbuf = [0u8; 8];
+        assert_eq!(b2p(&[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]).recv_frame(&mut rcvbuf).unwrap(), Frame::Ack);
+        assert_eq!(b2p(&[0x01, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00]).recv_frame(&mut rcvbuf).unwrap(), Frame::Nack);
+        assert_eq!(b2p(&[0x01, 0x00, 0x00, 0xFF, 0x01, 0xFF, 0x7F, 0x81, 0x00]).recv_frame(&mut rcvbuf).unwrap(), Frame::ErrorFrame);
+        assert_eq!(b2p(&[0x01, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x2A, 0x01]).recv_frame(&mut rcvbuf).unwrap(), Frame::Data(1));
+    }
+
+    #[test]
+    fn recv_ack_not_ready() {
+        use super::PN532Proto;
+        use ::bus::BusyWait;
+        use ::bus::test::StatusReady;
+        use ::std::time::Duration;
+
+        // Not ready first, BusyWait polls again.
+        let reads: [&[u8]; 2] = [&[0x00, 0x00, 0x00], &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]];
+        let mut proto = PN532Proto::new(BusyWait::with_delay(Scripted { reads: &reads, count: 0 }, Duration::from_millis(0)));
+        assert_eq!(proto.recv_ack().unwrap(), 1);
+
+        // Ready is reported separately, so the first byte isn't the ready flag.
+        let mut proto = PN532Proto::new(BusyWait::with_delay(StatusReady::new(&[0x00, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]), Duration::from_millis(0)));
+        assert_eq!(proto.recv_ack().unwrap(), 1);
+
+        let reads: [&[u8]; 1] = [&[0x01, 0x33, 0x00, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00]];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        assert_eq!(proto.recv_ack().unwrap(), 3);
+
+        let reads: [&[u8]; 1] = [&[0x01, 0x00, 0x00, 0x00]];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        assert_matches!(proto.recv_ack().unwrap_err(), ::error::RecvError::UnexpectedEnd);
+
+        let reads: [&[u8]; 1] = [&[0x01, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00]];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        assert_matches!(proto.recv_ack().unwrap_err(), ::error::RecvError::UnexpectedFrame(super::Frame::Nack));
+    }
+
+    #[test]
+    fn response_header() {
+        use super::ResponseHeader;
+        use ::error::{RecvError, DataError};
+
+        assert_eq!(ResponseHeader::parse::<io::Error>(&[0x15], 0x14).unwrap(), ResponseHeader { response_code: 0x15, payload_len: 0 });
+        assert_eq!(ResponseHeader::parse::<io::Error>(&[0x03, 0x32, 0x01], 0x02).unwrap().payload_len, 2);
+        assert_matches!(ResponseHeader::parse::<io::Error>(&[], 0x14), Err(RecvError::UnexpectedEnd));
+        assert_matches!(ResponseHeader::parse::<io::Error>(&[0x41], 0x14), Err(RecvError::InvalidData(DataError::InvalidByte(0x41, _))));
+    }
+
+    #[test]
+    fn scan_limit() {
+        use super::PN532Proto;
+
+        let mut junk = [0x55u8; 300];
+        junk[0] = 0x01;
+        junk[290..].copy_from_slice(&[0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x03, 0x28, 0x00, 0x00]);
+        let mut rcvbuf = [0u8; 8];
+        let reads: [&[u8]; 2] = [&junk, &junk];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        let mut scratch = [0u8; 300];
+        assert_eq!(proto.recv_into(&mut scratch, &mut rcvbuf).unwrap(), 1);
+        proto.set_max_scan_len(256);
+        assert_matches!(proto.recv_into(&mut scratch, &mut rcvbuf).unwrap_err(), ::error::RecvError::ScanLimitExceeded(256));
+    }
+
+    #[test]
+    fn ack_then_error_frame() {
+        use super::PN532Proto;
+        use ::error::{CommError, RecvError};
+
+        let reads: [&[u8]; 2] = [
+            &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00],
+            &[0x01, 0x00, 0x00, 0xFF, 0x01, 0xFF, 0x7F, 0x81, 0x00],
+        ];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        let mut rcvbuf = [0u8; 8];
+        proto.send_wait_ack(&[0x4A, 0x01, 0x00]).unwrap();
+        assert_matches!(proto.recv_reply_ack(&mut rcvbuf).unwrap_err(), CommError::RecvError(RecvError::ErrorFrame));
+
+        // Error frame read together with the ACK
+        let reads: [&[u8]; 1] = [&[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x01, 0xFF, 0x7F, 0x81, 0x00]];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        proto.send_wait_ack(&[0x4A, 0x01, 0x00]).unwrap();
+        assert_matches!(proto.recv_reply_ack(&mut rcvbuf).unwrap_err(), CommError::RecvError(RecvError::ErrorFrame));
+        assert_eq!(proto.device.count, 1);
+    }
+
+    #[test]
+    fn ack_with_response() {
+        use super::PN532Proto;
+
+        let reads: [&[u8]; 2] = [
+            &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x03, 0x28, 0x00],
+            &[0x01, 0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x05, 0x26, 0x00],
+        ];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        let mut rcvbuf = [0u8; 8];
+        assert_eq!(proto.recv_ack().unwrap(), 1);
+        assert_eq!(proto.recv(&mut rcvbuf).unwrap(), 1);
+        assert_eq!(rcvbuf[0], 0x03);
+        assert_eq!(proto.device.count, 1);
+        assert_eq!(proto.recv(&mut rcvbuf).unwrap(), 1);
+        assert_eq!(rcvbuf[0], 0x05);
+
+        // Response cut off by the end of the read is read again.
+        let reads: [&[u8]; 2] = [
+            &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x02],
+            &[0x01, 0x00, 0x00, 0xFF, 0x02, 0xFE, 0xD5, 0x05, 0x26, 0x00],
+        ];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        assert_eq!(proto.recv_ack().unwrap(), 1);
+        assert_eq!(proto.recv(&mut rcvbuf).unwrap(), 1);
+        assert_eq!(rcvbuf[0], 0x05);
+        assert_eq!(proto.stats(), Default::default());
+    }
+
+    #[test]
+    fn recv_truncated() {
+        use super::PN532Proto;
+
+        let reads: [&[u8]; 2] = [
+            &[0x01, 0x00, 0x00, 0xFF, 0x04, 0xFC, 0xD5, 0x41, 0x00, 0xAA, 0x40, 0x00],
+            &[0x01, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x04, 0xFC, 0xD5, 0x41, 0x00, 0xAA, 0x40, 0x00],
+        ];
+        let mut proto = PN532Proto::new(Scripted { reads: &reads, count: 0 });
+        let mut rcvbuf = [0u8; 2];
+        assert_matches!(proto.recv(&mut rcvbuf), Err(::error::RecvError::Truncated { copied: 2, total: 3 }));
+        assert_eq!(rcvbuf, [0x41, 0x00]);
+
+        // Same for response read together with ACK.
+        assert_eq!(proto.recv_ack().unwrap(), 1);
+        assert_matches!(proto.recv(&mut rcvbuf), Err(::error::RecvError::Truncated { copied: 2, total: 3 }));
+    }
+
     #[test]
     fn send() {
         use ::bus::BusyWait;
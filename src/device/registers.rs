@@ -0,0 +1,83 @@
+//! Addresses of PN532 registers and helper for writing several of them at once.
+//!
+//! Both are meant to be used with `PN532::read_registers` and `PN532::write_registers`,
+//! which still accept raw addresses.
+
+/// Registers of Contactless Interface Unit.
+pub mod ciu {
+    pub const MODE: u16 = 0x6301;
+    pub const TX_MODE: u16 = 0x6302;
+    pub const RX_MODE: u16 = 0x6303;
+    pub const TX_CONTROL: u16 = 0x6304;
+    pub const TX_AUTO: u16 = 0x6305;
+    pub const TX_SEL: u16 = 0x6306;
+    pub const RX_SEL: u16 = 0x6307;
+    pub const RX_THRESHOLD: u16 = 0x6308;
+    pub const DEMOD: u16 = 0x6309;
+    pub const MANUAL_RCV: u16 = 0x630D;
+    pub const TYPE_B: u16 = 0x630E;
+    pub const GS_N_OFF: u16 = 0x6313;
+    pub const MOD_WIDTH: u16 = 0x6314;
+    pub const TX_BIT_PHASE: u16 = 0x6315;
+    pub const RF_CFG: u16 = 0x6316;
+    pub const GS_N_ON: u16 = 0x6317;
+    pub const CW_GS_P: u16 = 0x6318;
+    pub const MOD_GS_P: u16 = 0x6319;
+    pub const COMMAND: u16 = 0x6331;
+    pub const ERROR: u16 = 0x6336;
+    pub const STATUS_1: u16 = 0x6337;
+    pub const STATUS_2: u16 = 0x6338;
+    pub const FIFO_DATA: u16 = 0x6339;
+    pub const FIFO_LEVEL: u16 = 0x633A;
+    pub const CONTROL: u16 = 0x633C;
+    pub const BIT_FRAMING: u16 = 0x633D;
+    pub const COLL: u16 = 0x633E;
+}
+
+/// Maximum number of registers written by single WriteRegister command.
+pub const MAX_REGISTER_WRITES: usize = 84;
+
+/// Collects register writes, so they can be sent in single command.
+///
+/// ```
+/// use pn532::registers::{ciu, RegisterWrites};
+///
+/// let writes = RegisterWrites::new()
+///     .set(ciu::RX_THRESHOLD, 0x85)
+///     .set(ciu::GS_N_ON, 0xF4);
+/// assert_eq!(writes.as_slice(), &[(0x6308, 0x85), (0x6317, 0xF4)]);
+/// ```
+#[derive(Copy, Clone)]
+pub struct RegisterWrites {
+    writes: [(u16, u8); MAX_REGISTER_WRITES],
+    len: usize,
+}
+
+impl RegisterWrites {
+    pub fn new() -> Self {
+        RegisterWrites {
+            writes: [(0, 0); MAX_REGISTER_WRITES],
+            len: 0,
+        }
+    }
+
+    /// Adds write of `value` to register at `address`.
+    ///
+    /// Panics if there are already `MAX_REGISTER_WRITES` writes.
+    pub fn set(mut self, address: u16, value: u8) -> Self {
+        assert!(self.len < MAX_REGISTER_WRITES, "too many register writes");
+        self.writes[self.len] = (address, value);
+        self.len += 1;
+        self
+    }
+
+    pub fn as_slice(&self) -> &[(u16, u8)] {
+        &self.writes[..self.len]
+    }
+}
+
+impl Default for RegisterWrites {
+    fn default() -> Self {
+        RegisterWrites::new()
+    }
+}
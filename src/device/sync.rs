@@ -0,0 +1,70 @@
+//! Wrapper of `PN532` which returns owned data, so it can be easily shared between threads.
+
+use ::bus;
+use ::error::CommResult;
+use super::{PN532, SAMMode};
+use super::tags_internal::{TagListOptions, TagBuffer, Tags, TagSnapshot, ISO14443AListOptions};
+
+/// Owns PN532 together with buffer for tag data.
+///
+/// Unlike `PN532`, none of its methods return values borrowing it, so it can be put
+/// into `Arc<Mutex<_>>` and used from multiple threads without fighting the lifetimes.
+pub struct SyncPN532<D: bus::WaitRead + bus::BusWrite> {
+    pn532: PN532<D>,
+    tag_buf: TagBuffer,
+}
+
+impl<D: bus::WaitRead + bus::BusWrite> SyncPN532<D> {
+    pub fn new(device: D) -> Self {
+        SyncPN532::from_pn532(PN532::new(device))
+    }
+
+    pub fn from_pn532(pn532: PN532<D>) -> Self {
+        SyncPN532 {
+            pn532,
+            tag_buf: TagBuffer::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> PN532<D> {
+        self.pn532
+    }
+
+    pub fn sam_configure(&mut self, mode: SAMMode) -> CommResult<(), D::ReadError, D::WriteError> {
+        self.pn532.sam_configure(mode)
+    }
+
+    pub fn sam_mode(&self) -> Option<SAMMode> {
+        self.pn532.sam_mode()
+    }
+
+    /// Lists tags and passes them to `f`, which should copy out whatever it needs.
+    ///
+    /// The tags borrow internal buffer, so they can't outlive the call.
+    pub fn with_tags<'a, O, F, T>(&'a mut self, options: O, f: F) -> CommResult<T, D::ReadError, D::WriteError>
+        where O: TagListOptions<'a>, F: FnOnce(Tags<'a, 'a, O::Response, PN532<D>>) -> T {
+        self.pn532.list_tags(options, &mut self.tag_buf).map(f)
+    }
+
+    /// Lists ISO14443A tags and returns their copies.
+    pub fn list_iso14443a(&mut self, options: ISO14443AListOptions) -> CommResult<Vec<TagSnapshot>, D::ReadError, D::WriteError> {
+        self.with_tags(options, |tags| {
+            let mut snapshots = Vec::with_capacity(tags.count());
+            if tags.count() > 0 {
+                let mut tag = Some(tags.first());
+                while let Some(t) = tag {
+                    snapshots.push(t.to_owned());
+                    tag = t.next();
+                }
+            }
+            snapshots
+        })
+    }
+
+    /// Sends data to tag with given number and returns its response.
+    pub fn transceive(&mut self, tag_number: u8, data_to_tag: &[u8]) -> CommResult<Vec<u8>, D::ReadError, D::WriteError> {
+        let mut buf = [0u8; 256];
+        let len = self.pn532.transceive(tag_number, data_to_tag, &mut buf)?;
+        Ok(buf[..len].to_vec())
+    }
+}
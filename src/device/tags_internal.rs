@@ -1,3 +1,6 @@
+use ::error::{InvalidUidLength, UnsupportedTagLimit, InvalidTimeSlotNumber, WriteTrailerError};
+use super::{TargetStatus, Baudrate};
+
 // ========================== Traits ==========================
 pub trait TagResponse<'s>: 's + Sized {
     fn new(response: &'s [u8]) -> Self;
@@ -6,11 +9,23 @@ pub trait TagResponse<'s>: 's + Sized {
     fn len(&self) -> usize;
     fn next(self) -> Self {
         let len = self.len();
-        Self::new(&self.into_buf()[len..])
+        let buf = self.into_buf();
+        Self::new(&buf[::core::cmp::min(len, buf.len())..])
     }
+    /// Logical number (Tg) assigned to the tag by PN532, 1 or 2, which addresses it in InDataExchange.
+    ///
+    /// It's the first byte of the tag's part of the listing response.
     fn tag_num(&self) -> u8 {
         self.buf()[0]
     }
+    /// Maximum frame size the tag is able to receive, if it's limited.
+    fn max_frame_size(&self) -> Option<usize> {
+        None
+    }
+    /// Identifier of the tag (UID, JEWELID, ...), if the response contains one.
+    fn uid(&self) -> Option<&[u8]> {
+        None
+    }
 }
 
 pub trait TagResponseMarker<'s>: TagResponse<'s> {}
@@ -21,45 +36,234 @@ pub trait TagListOptions<'a> {
     type Response: TagResponse<'a>;
 
     fn fill_buf(&self, buf: &mut [u8]) -> usize;
+
+    /// Code of the command used for listing, InListPassiveTarget by default.
+    fn command_code(&self) -> u8 {
+        0x4A
+    }
+
+    /// Whether halted tags should be woken up before listing.
+    fn wake_up(&self) -> bool {
+        false
+    }
+}
+
+/// Baud rate and modulation type (BrTy) of targets listed by InListPassiveTarget.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BaudType {
+    /// 106 kbps type A (ISO14443A, MIFARE).
+    ISO14443A106,
+    /// 212 kbps FeliCa.
+    FeliCa212,
+    /// 424 kbps FeliCa.
+    FeliCa424,
+    /// 106 kbps type B (ISO14443-3B).
+    ISO14443B106,
+    /// 106 kbps Innovision Jewel.
+    Jewel106,
+}
+
+impl BaudType {
+    pub fn code(self) -> u8 {
+        match self {
+            BaudType::ISO14443A106 => 0x00,
+            BaudType::FeliCa212 => 0x01,
+            BaudType::FeliCa424 => 0x02,
+            BaudType::ISO14443B106 => 0x03,
+            BaudType::Jewel106 => 0x04,
+        }
+    }
+}
+
+/// Type of tags listed by InListPassiveTarget, tying the options to the response and baud type.
+///
+/// Every protocol is also `TagListOptions`, so it can be passed to `list_tags`.
+/// Its response can be also obtained from tags found by InAutoPoll, see `Tag::into_protocol`.
+pub trait Protocol<'r> {
+    type Response: TagResponse<'r>;
+
+    /// Baud rate and modulation type (BrTy) of the tags.
+    fn baud_type(&self) -> BaudType;
+
+    /// Whether tags reported by InAutoPoll as `target` have `Response` layout.
+    fn is_target(target: PollTarget) -> bool;
+
+    /// Maximum number of tags to activate (MaxTg).
+    fn max_tags(&self) -> u8;
+
+    /// Writes initiator data following BrTy and returns their length. No data are sent by default.
+    fn fill_initiator_data(&self, _buf: &mut [u8]) -> usize {
+        0
+    }
+
+    /// Whether halted tags should be woken up before listing.
+    fn wake_up(&self) -> bool {
+        false
+    }
+}
+
+impl<'r, T: Protocol<'r>> TagListOptions<'r> for T {
+    type Response = T::Response;
+
+    fn fill_buf(&self, buf: &mut [u8]) -> usize {
+        buf[0] = self.max_tags();
+        buf[1] = self.baud_type().code();
+        2 + self.fill_initiator_data(&mut buf[2..])
+    }
+
+    fn wake_up(&self) -> bool {
+        Protocol::wake_up(self)
+    }
 }
 
 pub trait PN532Transceive {
     type TransceiveError;
 
     fn transceive(&mut self, tag_number: u8, data_to_tag: &[u8], data_from_tag: &mut [u8]) -> Result<usize, Self::TransceiveError>;
+
+    /// Same as `transceive`, but splits `data_to_tag` into frames of at most `max_frame_size` bytes.
+    fn transceive_fragmented(&mut self, tag_number: u8, max_frame_size: usize, data_to_tag: &[u8], data_from_tag: &mut [u8]) -> Result<usize, Self::TransceiveError>;
+
+    /// Maximum length of data which can be sent to tag in single frame.
+    fn max_frame_size(&self) -> usize;
+
+    /// Sends HLTA to the last activated ISO14443A tag, treating no response as success.
+    fn halt_iso14443a(&mut self) -> Result<(), Self::TransceiveError>;
+
+    /// Returns status of the target with given number reported by GetGeneralStatus,
+    /// `None` if PN532 doesn't handle such target.
+    fn target_status(&mut self, tag_number: u8) -> Result<Option<TargetStatus>, Self::TransceiveError>;
+
+    /// Switches communication with the target to `baudrate` in both directions,
+    /// returning `false` if the target rejected it.
+    fn set_baudrate(&mut self, tag_number: u8, baudrate: Baudrate) -> Result<bool, Self::TransceiveError>;
 }
 
 // ========================== Tags ==========================
 pub struct TagBuffer {
     buf: [u8; 256],
+    // length of the response received into buf
+    len: usize,
 }
 
 impl TagBuffer {
     pub fn new() -> Self {
-        // This is safe because buffer can't be read before it's written
-        // and even then, just what's been written.
-        unsafe {
-            ::core::mem::uninitialized()
+        TagBuffer {
+            buf: [0; 256],
+            len: 0,
+        }
+    }
+
+    // Gives access to the whole buffer, so the response can be received into it.
+    pub(crate) fn raw_mut(&mut self) -> &mut [u8; 256] {
+        &mut self.buf
+    }
+
+    pub(crate) fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    // Responses of ISO14443A tags in the buffer, none if it doesn't contain a complete listing.
+    pub(crate) fn iso14443a_infos(&self) -> TagInfos<'_, ISO14443A<'_>> {
+        TagInfos {
+            data: &self.buf[2..],
+            remaining: if self.len >= 2 { self.buf[1] as usize } else { 0 },
+            _phantom: Default::default(),
+        }
+    }
+
+    // Removes responses of ISO14443A tags for which `keep` returns false, moving the rest to the front.
+    pub(crate) fn retain_iso14443a<F: FnMut(&ISO14443A) -> bool>(&mut self, mut keep: F) {
+        if self.len < 2 {
+            return;
+        }
+        let mut read = 2;
+        let mut write = 2;
+        let mut kept = 0;
+        for _ in 0..self.buf[1] {
+            let len = ::core::cmp::min(ISO14443A::new(&self.buf[read..]).len(), self.len - read);
+            if keep(&ISO14443A::new(&self.buf[read..(read + len)])) {
+                self.buf.copy_within(read..(read + len), write);
+                write += len;
+                kept += 1;
+            }
+            read += len;
+        }
+        self.buf[1] = kept;
+        self.len = write;
+    }
+}
+
+impl Default for TagBuffer {
+    fn default() -> Self {
+        TagBuffer::new()
+    }
+}
+
+/// Tag buffer allocated on heap.
+///
+/// Unlike `TagBuffer`, moving it only moves the pointer, so it's cheap to store in long-lived
+/// structs together with `PN532`. It dereferences to `TagBuffer`, so it can be passed
+/// to `list_tags` and friends as `&mut buf`.
+#[cfg(feature = "std")]
+pub struct HeapTagBuffer {
+    buf: Box<TagBuffer>,
+}
+
+#[cfg(feature = "std")]
+impl HeapTagBuffer {
+    pub fn new() -> Self {
+        HeapTagBuffer {
+            buf: Box::default(),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl Default for HeapTagBuffer {
+    fn default() -> Self {
+        HeapTagBuffer::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::core::ops::Deref for HeapTagBuffer {
+    type Target = TagBuffer;
+
+    fn deref(&self) -> &TagBuffer {
+        &self.buf
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::core::ops::DerefMut for HeapTagBuffer {
+    fn deref_mut(&mut self) -> &mut TagBuffer {
+        &mut self.buf
+    }
+}
+
 pub struct Tags<'p, 'r, R: 'r + TagResponse<'r>, P: 'p + PN532Transceive> {
-    response: R,
+    // responses of all tags
+    data: &'r [u8],
     // pn532 which detected the tags
     pn532: &'p mut P,
     count: usize,
-    // Why the hell is this needed if 'r is actually used in R?
-    _phantom: ::core::marker::PhantomData<&'r ()>,
+    raw_len: usize,
+    _phantom: ::core::marker::PhantomData<R>,
 }
 
 impl<'p, 'r, R: 'r + TagResponse<'r>, P: 'p + PN532Transceive> Tags<'p, 'r, R, P> {
-    // Unsafe because TagBuffer is not guaranteed to be initialized
+    /// Interprets the buffer as response to InListPassiveTarget.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must contain a valid response of the type `R`.
     pub unsafe fn new(buf: &'r TagBuffer, pn532: &'p mut P) -> Self {
         Tags {
-            response: R::new(&buf.buf[2..]),
-            pn532: pn532,
+            data: &buf.buf[2..],
+            pn532,
             count: buf.buf[1] as usize,
+            raw_len: buf.len,
             _phantom: Default::default(),
         }
     }
@@ -68,10 +272,53 @@ impl<'p, 'r, R: 'r + TagResponse<'r>, P: 'p + PN532Transceive> Tags<'p, 'r, R, P
         self.count
     }
 
+    /// Length of the raw InListPassiveTarget response, including response code and number of tags.
+    ///
+    /// Useful for checking that the tags were parsed from the data actually received.
+    pub fn raw_len(&self) -> usize {
+        self.raw_len
+    }
+
+    /// Returns information about the tags without giving up the ability to communicate with them.
+    ///
+    /// The information borrows only the tag buffer, so it can be kept while using `first` or `nth`.
+    pub fn infos(&self) -> TagInfos<'r, R> {
+        TagInfos {
+            data: self.data,
+            remaining: self.count,
+            _phantom: Default::default(),
+        }
+    }
+
+    /// Drops the second tag if it has the same UID as the first one.
+    ///
+    /// RF glitches occasionally make PN532 report single card twice,
+    /// which would cause processing it twice.
+    pub fn deduplicate(mut self) -> Self {
+        if self.count == 2 {
+            let mut infos = self.infos();
+            if let (Some(first), Some(second)) = (infos.next(), infos.next()) {
+                if first.uid().is_some() && first.uid() == second.uid() {
+                    self.count = 1;
+                }
+            }
+        }
+        self
+    }
+
+    /// Returns tag at `index`, skipping the preceding one.
+    pub fn nth(self, index: usize) -> Option<Tag<'p, 'r, R, P>> {
+        match index {
+            _ if index >= self.count => None,
+            0 => Some(self.first()),
+            _ => self.first().next(),
+        }
+    }
+
     pub fn first(self) -> Tag<'p, 'r, R, P> {
         let count = self.count();
         Tag {
-            response: self.response,
+            response: R::new(self.data),
             pn532: self.pn532,
             last: count != 2,
             _phantom: Default::default(),
@@ -79,6 +326,28 @@ impl<'p, 'r, R: 'r + TagResponse<'r>, P: 'p + PN532Transceive> Tags<'p, 'r, R, P
     }
 }
 
+/// Iterator over information about listed tags, see `Tags::infos`.
+pub struct TagInfos<'r, R: 'r + TagResponse<'r>> {
+    data: &'r [u8],
+    remaining: usize,
+    _phantom: ::core::marker::PhantomData<R>,
+}
+
+impl<'r, R: 'r + TagResponse<'r>> Iterator for TagInfos<'r, R> {
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let response = R::new(self.data);
+        self.data = &self.data[::core::cmp::min(response.len(), self.data.len())..];
+        self.remaining -= 1;
+        Some(response)
+    }
+}
+
 pub struct Tag<'p, 'r, R: 'r + TagResponse<'r>, P: 'p + PN532Transceive> {
     response: R,
     // pn532 which detected the tag
@@ -102,13 +371,67 @@ impl<'p, 'r, R: 'r + TagResponse<'r>, P: 'p + PN532Transceive> Tag<'p, 'r, R, P>
         }
     }
 
+    /// Sends data to the tag and receives its response.
+    ///
+    /// Data longer than `max_transceive_len` are split into several frames, so tags
+    /// with small buffer (signaled by FSC in ATS) don't reject them.
+    ///
+    /// Once the tag is released (e.g. by `PN532::release`), this fails with
+    /// `Pn532Status::TargetReleased` and the `Tag` should be discarded.
+    /// `Pn532Status::NotAcceptable` is reported if PN532 rejected the exchange
+    /// while still handling the tag, in which case the `Tag` remains valid.
     pub fn transceive(&mut self, data_to_tag: &[u8], data_from_tag: &mut [u8]) -> Result<usize, P::TransceiveError> {
-        self.pn532.transceive(self.response.tag_num(), data_to_tag, data_from_tag)
+        let max_len = self.max_transceive_len();
+        let tag_num = self.response.tag_num();
+        debug_assert!(tag_num == 1 || tag_num == 2, "invalid target number {}", tag_num);
+        self.pn532.transceive_fragmented(tag_num, max_len, data_to_tag, data_from_tag)
+    }
+
+    /// Maximum length of data which both PN532 and the tag can handle in single frame.
+    pub fn max_frame_size(&self) -> usize {
+        use ::core::cmp::min;
+
+        let pn532_max = self.pn532.max_frame_size();
+        self.response.max_frame_size().map_or(pn532_max, |tag_max| min(tag_max, pn532_max))
+    }
+
+    /// Queries PN532 for status of this target, `None` if it isn't handled by PN532 anymore.
+    pub fn target_status(&mut self) -> Result<Option<TargetStatus>, P::TransceiveError> {
+        self.pn532.target_status(self.response.tag_num())
+    }
+
+    /// Queries PN532 for the data rate currently used for receiving from this target.
+    ///
+    /// Useful to decide whether switching to higher rate (PSL) is worthwhile.
+    pub fn baudrate(&mut self) -> Result<Option<Baudrate>, P::TransceiveError> {
+        Ok(self.target_status()?.map(|status| status.rx_baudrate))
+    }
+
+    /// Switches communication with the tag to faster `baudrate`, returning the rate used afterwards.
+    ///
+    /// The tag must support ISO14443-4 (or NFC-DEP), which allows changing the rate after activation.
+    /// PN532 handles the new rate itself, so following `transceive` calls are simply faster.
+    /// If the tag rejects the change, the current rate is queried and returned,
+    /// 106 kbps if PN532 doesn't report any.
+    pub fn boost_speed(&mut self, baudrate: Baudrate) -> Result<Baudrate, P::TransceiveError> {
+        if self.pn532.set_baudrate(self.response.tag_num(), baudrate)? {
+            Ok(baudrate)
+        } else {
+            Ok(self.baudrate()?.unwrap_or(Baudrate::Br106))
+        }
+    }
+
+    /// Maximum length of data `transceive` sends in single frame.
+    ///
+    /// Same as `max_frame_size`, but never zero, so that fragmenting always makes progress.
+    pub fn max_transceive_len(&self) -> usize {
+        ::core::cmp::max(self.max_frame_size(), 1)
     }
 }
 
 // ======================= Specific tag impls =======================
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TagNumLimit {
     One,
     Two,
@@ -168,8 +491,9 @@ impl<'a> TagResponse<'a> for ISO14443A<'a> {
         }
     }
 
+    // Tg, SENS_RES (2 bytes), SEL_RES, NFCID length, NFCID and ATS including its length byte
     fn len(&self) -> usize {
-        self.id_len() + self.ats_len() + 4
+        5 + self.id_len() + self.ats_len()
     }
 
     fn buf(&self) -> &[u8] {
@@ -179,143 +503,1662 @@ impl<'a> TagResponse<'a> for ISO14443A<'a> {
     fn into_buf(self) -> &'a [u8] {
         self.data
     }
+
+    // ISO14443-4 frame also contains PCB and two CRC bytes.
+    fn max_frame_size(&self) -> Option<usize> {
+        if self.ats_len() > 0 {
+            Some(Ats::new(self.ats()).max_frame_size() - 3)
+        } else {
+            None
+        }
+    }
+
+    fn uid(&self) -> Option<&[u8]> {
+        self.checked_id()
+    }
 }
 
 impl<'a> ISO14443A<'a> {
+    pub fn sens_res(&self) -> u16 {
+        self.checked_sens_res().unwrap_or(0)
+    }
+
+    /// Returns SAK, see `Tag::sel_res` for meaning of its bits.
+    pub fn sel_res(&self) -> u8 {
+        self.checked_sel_res().unwrap_or(0)
+    }
+
+    pub fn is_iso14443_4(&self) -> bool {
+        self.sel_res() & 0x20 != 0
+    }
+
+    pub fn supports_nfc_dep(&self) -> bool {
+        self.sel_res() & 0x40 != 0
+    }
+
+    pub fn id(&self) -> &'a [u8] {
+        self.checked_id().unwrap_or(&[])
+    }
+
+    pub fn is_uid_complete(&self) -> bool {
+        const CASCADE_BIT: u8 = 0x04;
+        const CASCADE_TAG: u8 = 0x88;
+
+        let id = match self.checked_id() {
+            Some(id) => id,
+            None => return false,
+        };
+        match CascadeLevel::from_uid_len(id.len()) {
+            Some(CascadeLevel::Single) => self.sel_res() & CASCADE_BIT == 0 && id[0] != CASCADE_TAG,
+            Some(_) => self.sel_res() & CASCADE_BIT == 0,
+            None => false,
+        }
+    }
+
     pub fn id_len(&self) -> usize {
-        self.data[4] as usize
+        self.data.get(4).map_or(0, |len| *len as usize)
     }
 
+    /// Returns length of ATS including the length byte itself.
+    ///
+    /// PN532 only reports ATS of tags supporting ISO14443-4, it's zero for other tags.
     pub fn ats_len(&self) -> usize {
-        self.data[5 + self.id_len()] as usize
+        if !self.is_iso14443_4() {
+            return 0;
+        }
+        self.data.get(5 + self.id_len()).map_or(0, |len| *len as usize)
+    }
+
+    /// Returns ATS without the length byte.
+    pub fn ats(&self) -> &'a [u8] {
+        self.checked_ats().unwrap_or(&[])
+    }
+
+    /// Returns SENS_RES (ATQA), or `None` if the response is too short to contain it.
+    ///
+    /// Tags which didn't fully activate (e.g. damaged or partially shielded ones) may leave
+    /// their responses incomplete. The `checked_` accessors tell which fields are missing,
+    /// while the others return zero or empty slice for them.
+    pub fn checked_sens_res(&self) -> Option<u16> {
+        let bytes = self.data.get(1..3)?;
+        Some(((bytes[0] as u16) << 8) | (bytes[1] as u16))
+    }
+
+    /// Returns SEL_RES (SAK), or `None` if the response is too short to contain it.
+    pub fn checked_sel_res(&self) -> Option<u8> {
+        self.data.get(3).cloned()
+    }
+
+    /// Returns UID, or `None` if the response is too short to contain it.
+    pub fn checked_id(&self) -> Option<&'a [u8]> {
+        let len = *self.data.get(4)? as usize;
+        self.data.get(5..(5 + len))
+    }
+
+    /// Returns ATS without the length byte, or `None` if the response is too short to contain it.
+    pub fn checked_ats(&self) -> Option<&'a [u8]> {
+        if self.checked_sel_res()? & 0x20 == 0 {
+            return Some(&[]);
+        }
+        let ats_start = 5 + self.id_len();
+        // ats_len includes the length byte itself
+        let len = *self.data.get(ats_start)? as usize;
+        if len > 1 {
+            self.data.get((ats_start + 1)..(ats_start + len))
+        } else {
+            Some(&[])
+        }
+    }
+}
+
+/// Identifiers of ISO14443A tag, as returned by `Tags::summaries`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TagSummary<'a> {
+    /// SENS_RES
+    pub atqa: u16,
+    /// SEL_RES
+    pub sak: u8,
+    pub uid: &'a [u8],
+}
+
+impl<'p, 'r, P: 'p + PN532Transceive> Tags<'p, 'r, ISO14443A<'r>, P> {
+    /// Returns identifiers of all listed tags (PN532 lists at most two), e.g. for logging them.
+    ///
+    /// Like `infos`, this borrows only the tag buffer, so any tag can still be chosen afterwards.
+    pub fn summaries(&self) -> [Option<TagSummary<'r>>; 2] {
+        let mut summaries = [None; 2];
+        for (summary, info) in summaries.iter_mut().zip(self.infos()) {
+            *summary = Some(TagSummary {
+                atqa: info.sens_res(),
+                sak: info.sel_res(),
+                uid: info.id(),
+            });
+        }
+        summaries
     }
 }
 
 impl<'r, 'p, P: PN532Transceive> Tag<'p, 'r, ISO14443A<'r>, P> {
     pub fn sens_res(&self) -> u16 {
-        ((self.response.buf()[1] as u16) << 8) | (self.response.buf()[2] as u16)
+        self.response.sens_res()
     }
 
+    /// Returns SAK. Bits (counting from 0) meaningful in complete SAK are:
+    ///
+    /// * bit 5 (0x20) - the tag is compliant with ISO14443-4
+    /// * bit 6 (0x40) - the tag is compliant with ISO18092 (NFC-DEP)
+    ///
+    /// Other bits are RFU, though MIFARE Classic tags use them to signal the type of tag.
     pub fn sel_res(&self) -> u8 {
-        self.response.buf()[3]
+        self.response.sel_res()
+    }
+
+    /// Returns true if the tag supports ISO14443-4, so APDUs can be exchanged with it.
+    pub fn is_iso14443_4(&self) -> bool {
+        self.response.is_iso14443_4()
+    }
+
+    /// Returns true if the tag supports NFC-DEP protocol (ISO18092).
+    pub fn supports_nfc_dep(&self) -> bool {
+        self.response.supports_nfc_dep()
     }
 
     pub fn id_len(&self) -> usize {
         self.response.id_len()
     }
 
+    /// Returns UID of the tag.
+    ///
+    /// PN532 performs anticollision through all cascade levels itself, so this is the complete
+    /// UID of 4, 7 or 10 bytes without cascade tags, see `is_uid_complete`.
     pub fn id(&self) -> &[u8] {
-        &self.response.buf()[5..(5 + self.id_len())]
+        self.response.id()
+    }
+
+    /// Checks that `id` is complete UID, so it can be used for identification.
+    ///
+    /// That's the case if it has valid length, the cascade bit (0x04) of SAK is cleared and single size
+    /// UID doesn't start with cascade tag (0x88). It can only fail if PN532 reported garbage.
+    pub fn is_uid_complete(&self) -> bool {
+        self.response.is_uid_complete()
     }
 
     pub fn ats_len(&self) -> usize {
         self.response.ats_len()
     }
 
+    /// Returns ATS without the length byte.
     pub fn ats(&self) -> &[u8] {
-        &self.response.buf()[(5 + self.id_len() + 1)..]
+        self.response.ats()
     }
-}
 
-pub struct ISO14443AListOptions<'id> {
-    pub limit: TagNumLimit,
-    pub uid: Option<&'id [u8]>,
-}
+    /// Parses ATS, so its interface bytes can be read easily.
+    pub fn parse_ats(&self) -> Ats<'_> {
+        Ats::new(self.ats())
+    }
 
-impl<'r, 'id> TagListOptions<'r> for ISO14443AListOptions<'id> {
-    type Response = ISO14443A<'r>;
+    /// Returns historical bytes of ATS, see `Ats::historical_bytes`.
+    pub fn historical_bytes(&self) -> &[u8] {
+        Ats::new(self.ats()).historical_bytes()
+    }
 
-    fn fill_buf(&self, buf: &mut [u8]) -> usize {
-        use ::core::cmp::min;
+    /// Halts the tag, so it doesn't respond again until it's removed and re-presented.
+    ///
+    /// This is the standard way to avoid reading the same tag repeatedly in a loop.
+    /// See `PN532::halt_iso14443a` for details.
+    pub fn halt(self) -> Result<(), P::TransceiveError> {
+        self.pn532.halt_iso14443a()
+    }
 
-        buf[0] = self.limit.into();
-        buf[1] = 0x00;
-        self.uid.map_or(2, |data| {
-            let to_copy = min(buf.len() - 2, data.len());
-            buf[2..(2 + to_copy)].copy_from_slice(&data[0..to_copy]);
-            to_copy + 2
-        })
+    /// Authenticates MIFARE Classic `block` (and thus its whole sector) using `key`.
+    ///
+    /// PN532 performs the Crypto1 handshake itself, failure is reported by it
+    /// as authentication error status.
+    pub fn mifare_authenticate(&mut self, block: u8, key_type: MifareKeyType, key: &[u8; 6]) -> Result<(), P::TransceiveError> {
+        let mut cmd = [0u8; 12];
+        cmd[0] = key_type.command_code();
+        cmd[1] = block;
+        cmd[2..8].copy_from_slice(key);
+        // Tags with double size UID use its last four bytes.
+        let id = self.id();
+        let id = &id[id.len().saturating_sub(4)..];
+        cmd[8..(8 + id.len())].copy_from_slice(id);
+        self.transceive(&cmd[..(8 + id.len())], &mut [])?;
+        Ok(())
+    }
+
+    /// Reads MIFARE `block` into `data`, returning the number of bytes received (16 normally).
+    ///
+    /// MIFARE Classic blocks must be authenticated first.
+    pub fn mifare_read(&mut self, block: u8, data: &mut [u8; 16]) -> Result<usize, P::TransceiveError> {
+        self.transceive(&[0x30, block], data)
+    }
+
+    /// Writes 16 bytes of `data` into MIFARE `block`, which must be authenticated first.
+    pub fn mifare_write(&mut self, block: u8, data: &[u8; 16]) -> Result<(), P::TransceiveError> {
+        let mut cmd = [0u8; 18];
+        cmd[0] = 0xA0;
+        cmd[1] = block;
+        cmd[2..].copy_from_slice(data);
+        self.transceive(&cmd, &mut [])?;
+        Ok(())
+    }
+
+    /// Writes `trailer` into the last block of MIFARE Classic `sector`.
+    ///
+    /// The sector must be authenticated with a key allowing the write.
+    /// Access bits are always encoded together with their inverted copy,
+    /// since a trailer with inconsistent access bits blocks the whole sector.
+    ///
+    /// Nothing is written if `sector` is greater than 39, the last sector of MIFARE Classic 4K.
+    pub fn write_trailer(&mut self, sector: u8, trailer: &SectorTrailer) -> Result<(), WriteTrailerError<P::TransceiveError>> {
+        let block = SectorTrailer::block(sector).ok_or(WriteTrailerError::InvalidSector(sector))?;
+        self.mifare_write(block, &trailer.to_bytes())?;
+        Ok(())
+    }
+
+    /// Copies information about the tag, so it can be kept after releasing PN532.
+    #[cfg(feature = "std")]
+    pub fn to_owned(&self) -> TagSnapshot {
+        TagSnapshot {
+            sens_res: self.sens_res(),
+            sel_res: self.sel_res(),
+            id: self.id().to_vec(),
+            ats: self.ats().to_vec(),
+        }
     }
 }
 
-/*
+/// Key used for MIFARE Classic authentication.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum PollingMethod {
-    Probabilistic,
-    Timeslot,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MifareKeyType {
+    A,
+    B,
 }
 
-impl PollingMethod {
-    fn code(self) -> u8 {
+impl MifareKeyType {
+    /// Code of MIFARE authentication command using this key.
+    pub fn command_code(self) -> u8 {
         match self {
-            PollingMethod::Probabilistic => 0x01,
-            PollingMethod::Timeslot => 0x00,
+            MifareKeyType::A => 0x60,
+            MifareKeyType::B => 0x61,
         }
     }
 }
 
-impl Default for PollingMethod {
-    fn default() -> Self {
-        PollingMethod::Timeslot
+/// Access conditions of the blocks in MIFARE Classic sector.
+///
+/// Each condition is formed by bits C1, C2 and C3 (in this order, C1 being the most significant
+/// one), see the datasheet of the tag for their meaning.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccessBits {
+    conditions: [u8; 4],
+}
+
+impl AccessBits {
+    /// Access bits of tags fresh from factory: data blocks accessible using any key,
+    /// keys and access bits writable using key A.
+    pub const TRANSPORT: AccessBits = AccessBits { conditions: [0b000, 0b000, 0b000, 0b001] };
+
+    /// Creates access bits from conditions of blocks 0-2 (or groups of blocks in big sectors)
+    /// and of the sector trailer, returns `None` if some of them has more than three bits.
+    pub fn new(conditions: [u8; 4]) -> Option<Self> {
+        if conditions.iter().any(|c| *c > 0b111) {
+            return None;
+        }
+        Some(AccessBits { conditions })
+    }
+
+    /// Decodes bytes 6-8 of sector trailer, returns `None` if they don't match their inverted copy.
+    pub fn from_bytes(bytes: [u8; 3]) -> Option<Self> {
+        let c1 = bytes[1] >> 4;
+        let c2 = bytes[2] & 0x0F;
+        let c3 = bytes[2] >> 4;
+        if bytes[0] & 0x0F != !c1 & 0x0F || bytes[0] >> 4 != !c2 & 0x0F || bytes[1] & 0x0F != !c3 & 0x0F {
+            return None;
+        }
+
+        let mut conditions = [0u8; 4];
+        for (i, condition) in conditions.iter_mut().enumerate() {
+            *condition = ((c1 >> i) & 1) << 2 | ((c2 >> i) & 1) << 1 | ((c3 >> i) & 1);
+        }
+        Some(AccessBits { conditions })
+    }
+
+    /// Access condition of block `index` (3 is the sector trailer).
+    pub fn condition(&self, index: usize) -> u8 {
+        self.conditions[index]
+    }
+
+    /// Encodes the access bits into bytes 6-8 of sector trailer.
+    pub fn to_bytes(&self) -> [u8; 3] {
+        let (mut c1, mut c2, mut c3) = (0u8, 0u8, 0u8);
+        for (i, condition) in self.conditions.iter().enumerate() {
+            c1 |= ((condition >> 2) & 1) << i;
+            c2 |= ((condition >> 1) & 1) << i;
+            c3 |= (condition & 1) << i;
+        }
+        [(!c2 & 0x0F) << 4 | (!c1 & 0x0F), c1 << 4 | (!c3 & 0x0F), c3 << 4 | c2]
     }
 }
 
+/// Content of the last block of MIFARE Classic sector.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct ISO14443BListOptions {
-    pub limit: TagNumLimit,
-    pub afi: u8,
-    pub polling_method: Option<PollingMethod>,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SectorTrailer {
+    pub key_a: [u8; 6],
+    pub access_bits: AccessBits,
+    /// General purpose byte following the access bits, not used by the tag itself.
+    pub gpb: u8,
+    pub key_b: [u8; 6],
 }
 
-impl TagListOptions for ISO14443BListOptions {
-    fn fill_buf(&self, buf: &mut [u8]) -> usize {
-        buf[0] = self.limit.into();
-        buf[1] = 0x03;
-        buf[2] = self.afi;
-        self.polling_method.map_or(3, |method| {
-            buf[3] = method.code();
-            4
-        })
+impl SectorTrailer {
+    /// Returns the number of the trailer block of `sector`.
+    ///
+    /// `None` if `sector` is greater than 39, the last sector of MIFARE Classic 4K.
+    pub fn block(sector: u8) -> Option<u8> {
+        // Sectors above 31 have 16 blocks instead of 4.
+        match sector {
+            0..=31 => Some(sector * 4 + 3),
+            32..=39 => Some(128 + (sector - 32) * 16 + 15),
+            _ => None,
+        }
+    }
+
+    /// Lays the trailer out as 16 bytes written to the tag.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..6].copy_from_slice(&self.key_a);
+        bytes[6..9].copy_from_slice(&self.access_bits.to_bytes());
+        bytes[9] = self.gpb;
+        bytes[10..].copy_from_slice(&self.key_b);
+        bytes
     }
 }
 
+/// Parsed ATS (Answer To Select) of ISO14443-4 tag.
+///
+/// Missing bytes are treated as absent, so values defined by ISO14443-4 are used instead.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum FeliCaBaudrate {
-    Br212,
-    Br424,
+pub struct Ats<'a> {
+    // ATS without the length byte (TL)
+    data: &'a [u8],
 }
 
-impl FeliCaBaudrate {
-    fn code(self) -> u8 {
-        match self {
-            FeliCaBaudrate::Br212 => 0x01,
-            FeliCaBaudrate::Br424 => 0x02,
+impl<'a> Ats<'a> {
+    /// Wraps ATS without the length byte (TL).
+    pub fn new(data: &'a [u8]) -> Self {
+        Ats {
+            data,
         }
     }
-}
 
-pub struct FeliCaListOptions {
-    pub limit: TagNumLimit,
-    pub baudrate: FeliCaBaudrate,
-    pub payload: [u8; 5],
-}
+    /// Returns the length byte, which includes itself.
+    pub fn tl(&self) -> u8 {
+        self.data.len() as u8 + 1
+    }
 
-impl TagListOptions for FeliCaListOptions {
-    fn fill_buf(&self, buf: &mut [u8]) -> usize {
-        buf[0] = self.limit.into();
-        buf[1] = self.baudrate.code();
-        buf[2..7].copy_from_slice(&self.payload);
-        7
+    /// Format byte. It contains FSCI and flags signaling presence of interface bytes.
+    pub fn t0(&self) -> Option<u8> {
+        self.data.first().cloned()
     }
-}
 
-pub struct JewelTagListOptions;
+    /// Interface byte TA(1) - supported bit rates.
+    pub fn ta(&self) -> Option<u8> {
+        self.interface_byte(0)
+    }
 
-impl TagListOptions for JewelTagListOptions {
-    fn fill_buf(&self, buf: &mut [u8]) -> usize {
-        buf[0] = 1;
-        buf[1] = 0x04;
-        2
+    /// Interface byte TB(1) - FWI and SFGI.
+    pub fn tb(&self) -> Option<u8> {
+        self.interface_byte(1)
     }
-}
-*/
+
+    /// Interface byte TC(1) - supported protocol options.
+    pub fn tc(&self) -> Option<u8> {
+        self.interface_byte(2)
+    }
+
+    /// Frame size for proximity card integer. Defaults to 2 if T0 is absent.
+    pub fn fsci(&self) -> u8 {
+        self.t0().map_or(2, |t0| t0 & 0x0F)
+    }
+
+    /// Maximum frame size in bytes the card is able to receive.
+    pub fn max_frame_size(&self) -> usize {
+        const FSC: [usize; 9] = [16, 24, 32, 40, 48, 64, 96, 128, 256];
+
+        // Values above 8 are RFU, which should be interpreted as 256.
+        FSC.get(self.fsci() as usize).cloned().unwrap_or(256)
+    }
+
+    /// Frame waiting time integer. Defaults to 4 if TB is absent.
+    pub fn fwi(&self) -> u8 {
+        // 15 is RFU, which should be interpreted as 4.
+        match self.tb().map(|tb| tb >> 4) {
+            Some(fwi) if fwi < 15 => fwi,
+            _ => 4,
+        }
+    }
+
+    /// Start-up frame guard time integer. Defaults to 0 if TB is absent.
+    pub fn sfgi(&self) -> u8 {
+        self.tb().map_or(0, |tb| tb & 0x0F)
+    }
+
+    /// Maximum time the card may take to respond.
+    ///
+    /// FWT = (256 * 16 / fc) * 2^FWI, where fc = 13.56 MHz
+    pub fn frame_waiting_time(&self) -> ::core::time::Duration {
+        let nanos = (4096u64 << self.fwi()) * 1_000_000_000 / 13_560_000;
+        ::core::time::Duration::from_nanos(nanos)
+    }
+
+    /// Historical bytes following the interface bytes, empty if there are none.
+    ///
+    /// They usually identify the card platform or operating system (e.g. DESFire, JavaCard).
+    pub fn historical_bytes(&self) -> &'a [u8] {
+        let start = match self.t0() {
+            Some(t0) => 1 + (0..3).filter(|i| t0 & (0x10 << i) != 0).count(),
+            None => return &[],
+        };
+        self.data.get(start..).unwrap_or(&[])
+    }
+
+    // Interface bytes follow T0 in order TA, TB, TC.
+    // Each of them is present only if the corresponding bit in T0 is set.
+    fn interface_byte(&self, index: usize) -> Option<u8> {
+        let t0 = self.t0()?;
+        if t0 & (0x10 << index) == 0 {
+            return None;
+        }
+
+        let preceding = (0..index).filter(|i| t0 & (0x10 << i) != 0).count();
+        self.data.get(1 + preceding).cloned()
+    }
+}
+
+/// Owned copy of information about ISO14443A tag.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TagSnapshot {
+    /// ATQA
+    pub sens_res: u16,
+    /// SAK
+    pub sel_res: u8,
+    /// UID
+    pub id: Vec<u8>,
+    /// ATS without the length byte
+    pub ats: Vec<u8>,
+}
+
+/// Owned information about a card of any supported type, as found by `PN532::poll_once`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DetectedCard {
+    ISO14443A(TagSnapshot),
+    /// FeliCa card identified by its IDm.
+    FeliCa { idm: [u8; 8] },
+    /// ISO14443B card identified by its PUPI.
+    ISO14443B { pupi: [u8; 4] },
+    /// Innovision Jewel tag identified by its JEWELID.
+    Jewel { id: [u8; 4] },
+    /// Card of other type or with target data too short for its type.
+    /// The data are as sent by PN532, without the tag number.
+    Other { target: PollTarget, data: Vec<u8> },
+}
+
+/// Number of anticollision loops needed to select ISO14443A tag.
+/// It's determined by length of the UID.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CascadeLevel {
+    /// 4-byte UID
+    Single,
+    /// 7-byte UID
+    Double,
+    /// 10-byte UID
+    Triple,
+}
+
+impl CascadeLevel {
+    /// Returns cascade level corresponding to UID length or `None` if the length is invalid.
+    pub fn from_uid_len(len: usize) -> Option<Self> {
+        match len {
+            4 => Some(CascadeLevel::Single),
+            7 => Some(CascadeLevel::Double),
+            10 => Some(CascadeLevel::Triple),
+            _ => None,
+        }
+    }
+
+    pub fn uid_len(self) -> usize {
+        match self {
+            CascadeLevel::Single => 4,
+            CascadeLevel::Double => 7,
+            CascadeLevel::Triple => 10,
+        }
+    }
+}
+
+/// UID of ISO14443A tag, guaranteed to have valid length.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Uid<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Uid<'a> {
+    /// Checks that the length of UID is 4, 7 or 10 bytes.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, InvalidUidLength> {
+        CascadeLevel::from_uid_len(bytes.len())
+            .map(|_| Uid { bytes })
+            .ok_or(InvalidUidLength(bytes.len()))
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn cascade_level(&self) -> CascadeLevel {
+        // Length was validated in constructor.
+        CascadeLevel::from_uid_len(self.bytes.len()).unwrap()
+    }
+
+    // PN532 expects the UID split into cascade levels, each but the last
+    // one starting with cascade tag.
+    fn fill_buf(&self, buf: &mut [u8]) -> usize {
+        const CASCADE_TAG: u8 = 0x88;
+
+        let mut pos = 0;
+        let mut uid = self.bytes;
+        while uid.len() > 4 {
+            buf[pos] = CASCADE_TAG;
+            buf[(pos + 1)..(pos + 4)].copy_from_slice(&uid[..3]);
+            pos += 4;
+            uid = &uid[3..];
+        }
+        buf[pos..(pos + 4)].copy_from_slice(uid);
+        pos + 4
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ISO14443AListOptions<'id> {
+    /// PN532 can activate up to two ISO14443A tags at once.
+    pub limit: TagNumLimit,
+    pub uid: Option<Uid<'id>>,
+    /// Activate also tags halted by HLTA.
+    ///
+    /// PN532 activates tags using REQA, to which only idle tags respond.
+    /// Halted tags respond only to WUPA, which InListPassiveTarget can't send,
+    /// so the RF field is switched off and on before listing instead.
+    /// This resets all tags in the field to idle state, reaching the same tags as WUPA would.
+    pub wake_up: bool,
+}
+
+impl<'r, 'id> Protocol<'r> for ISO14443AListOptions<'id> {
+    type Response = ISO14443A<'r>;
+
+    fn baud_type(&self) -> BaudType {
+        BaudType::ISO14443A106
+    }
+
+    fn is_target(target: PollTarget) -> bool {
+        target == PollTarget::ISO14443A
+    }
+
+    fn max_tags(&self) -> u8 {
+        self.limit.into()
+    }
+
+    fn fill_initiator_data(&self, buf: &mut [u8]) -> usize {
+        self.uid.map_or(0, |uid| uid.fill_buf(buf))
+    }
+
+    fn wake_up(&self) -> bool {
+        self.wake_up
+    }
+}
+
+/// Innovision Jewel tag.
+pub struct Jewel<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TagResponse<'a> for Jewel<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Jewel {
+            data: buf,
+        }
+    }
+
+    // Tg, SENS_RES (2 bytes) and JEWELID (4 bytes)
+    fn len(&self) -> usize {
+        7
+    }
+
+    fn buf(&self) -> &[u8] {
+        self.data
+    }
+
+    fn into_buf(self) -> &'a [u8] {
+        self.data
+    }
+
+    fn uid(&self) -> Option<&[u8]> {
+        self.checked_id()
+    }
+}
+
+impl<'a> Jewel<'a> {
+    /// Returns SENS_RES, or `None` if the response is too short to contain it.
+    pub fn checked_sens_res(&self) -> Option<u16> {
+        let bytes = self.data.get(1..3)?;
+        Some(((bytes[0] as u16) << 8) | (bytes[1] as u16))
+    }
+
+    /// Returns JEWELID, or `None` if the response is too short to contain it.
+    pub fn checked_id(&self) -> Option<&'a [u8]> {
+        self.data.get(3..7)
+    }
+}
+
+impl<'r, 'p, P: PN532Transceive> Tag<'p, 'r, Jewel<'r>, P> {
+    /// Returns SENS_RES, 0 if PN532 didn't report it.
+    pub fn sens_res(&self) -> u16 {
+        self.response.checked_sens_res().unwrap_or(0)
+    }
+
+    /// Returns JEWELID, empty if PN532 didn't report it.
+    pub fn id(&self) -> &[u8] {
+        self.response.checked_id().unwrap_or(&[])
+    }
+}
+
+/// Lists Innovision Jewel tags.
+///
+/// PN532 can activate only one Jewel tag at a time, so converting from `TagNumLimit::Two` fails.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct JewelTagListOptions {
+    _private: (),
+}
+
+impl JewelTagListOptions {
+    pub fn new() -> Self {
+        JewelTagListOptions {
+            _private: (),
+        }
+    }
+}
+
+impl Default for JewelTagListOptions {
+    fn default() -> Self {
+        JewelTagListOptions::new()
+    }
+}
+
+impl ::core::convert::TryFrom<TagNumLimit> for JewelTagListOptions {
+    type Error = UnsupportedTagLimit;
+
+    fn try_from(limit: TagNumLimit) -> Result<Self, Self::Error> {
+        match limit {
+            TagNumLimit::One => Ok(JewelTagListOptions::new()),
+            TagNumLimit::Two => Err(UnsupportedTagLimit(2)),
+        }
+    }
+}
+
+impl<'r> Protocol<'r> for JewelTagListOptions {
+    type Response = Jewel<'r>;
+
+    fn baud_type(&self) -> BaudType {
+        BaudType::Jewel106
+    }
+
+    fn is_target(target: PollTarget) -> bool {
+        target == PollTarget::Jewel
+    }
+
+    fn max_tags(&self) -> u8 {
+        1
+    }
+}
+
+/// Kind of target searched for by InAutoPoll and reported in its response.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PollTarget {
+    /// Any ISO14443A tag, including MIFARE.
+    ISO14443A,
+    FeliCa212,
+    FeliCa424,
+    ISO14443B,
+    Jewel,
+    /// Other type code, see the PN532 user manual.
+    Other(u8),
+}
+
+impl PollTarget {
+    fn code(self) -> u8 {
+        match self {
+            PollTarget::ISO14443A => 0x00,
+            PollTarget::FeliCa212 => 0x01,
+            PollTarget::FeliCa424 => 0x02,
+            PollTarget::ISO14443B => 0x03,
+            PollTarget::Jewel => 0x04,
+            PollTarget::Other(code) => code,
+        }
+    }
+
+    // PN532 reports more specific types than the generic ones it was asked for.
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x00 | 0x10 | 0x20 => PollTarget::ISO14443A,
+            0x01 | 0x11 => PollTarget::FeliCa212,
+            0x02 | 0x12 => PollTarget::FeliCa424,
+            0x03 | 0x23 => PollTarget::ISO14443B,
+            0x04 => PollTarget::Jewel,
+            other => PollTarget::Other(other),
+        }
+    }
+}
+
+impl From<BaudType> for PollTarget {
+    fn from(baud_type: BaudType) -> Self {
+        PollTarget::from_code(baud_type.code())
+    }
+}
+
+/// Polls for tags of several types at once using InAutoPoll.
+///
+/// Listed tags are `AutoPolled`, which can be converted to the specific type.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct AutoPollOptions<'t> {
+    /// Number of polling rounds, 0xFF means polling until a tag is found.
+    pub poll_count: u8,
+    /// Period between rounds in units of 150 ms.
+    pub period: u8,
+    /// Types of tags to look for, at most 15.
+    pub targets: &'t [PollTarget],
+}
+
+impl<'r, 't> TagListOptions<'r> for AutoPollOptions<'t> {
+    type Response = AutoPolled<'r>;
+
+    fn fill_buf(&self, buf: &mut [u8]) -> usize {
+        buf[0] = self.poll_count;
+        buf[1] = self.period;
+        for (dst, target) in buf[2..].iter_mut().zip(self.targets.iter().take(15)) {
+            *dst = target.code();
+        }
+        2 + self.targets.len().min(15)
+    }
+
+    fn command_code(&self) -> u8 {
+        0x60
+    }
+}
+
+/// Tag found by InAutoPoll. It can be of any type requested in `AutoPollOptions`.
+pub struct AutoPolled<'a> {
+    // type, length of target data and target data
+    data: &'a [u8],
+}
+
+impl<'a> TagResponse<'a> for AutoPolled<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        AutoPolled {
+            data: buf,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.data[1] as usize + 2
+    }
+
+    fn buf(&self) -> &[u8] {
+        self.data
+    }
+
+    fn into_buf(self) -> &'a [u8] {
+        self.data
+    }
+
+    // Target data start with the tag number.
+    fn tag_num(&self) -> u8 {
+        self.data[2]
+    }
+}
+
+impl<'r, 'p, P: PN532Transceive> Tag<'p, 'r, AutoPolled<'r>, P> {
+    pub fn target(&self) -> PollTarget {
+        PollTarget::from_code(self.response.data[0])
+    }
+
+    /// Target data as sent by PN532, starting with the tag number.
+    pub fn target_data(&self) -> &[u8] {
+        &self.response.data[2..self.response.len()]
+    }
+
+    /// Copies information identifying the tag, so it can be kept after releasing PN532.
+    #[cfg(feature = "std")]
+    pub fn to_detected(&self) -> DetectedCard {
+        let data = &self.target_data()[1..];
+        match self.target() {
+            PollTarget::ISO14443A => {
+                let tag = ISO14443A::new(self.target_data());
+                DetectedCard::ISO14443A(TagSnapshot {
+                    sens_res: tag.sens_res(),
+                    sel_res: tag.sel_res(),
+                    id: tag.id().to_vec(),
+                    ats: tag.ats().to_vec(),
+                })
+            },
+            // POL_RES length, response code and IDm
+            PollTarget::FeliCa212 | PollTarget::FeliCa424 if data.len() >= 10 => {
+                let mut idm = [0u8; 8];
+                idm.copy_from_slice(&data[2..10]);
+                DetectedCard::FeliCa { idm }
+            },
+            // ATQB starting with 0x50 followed by PUPI
+            PollTarget::ISO14443B if data.len() >= 5 => {
+                let mut pupi = [0u8; 4];
+                pupi.copy_from_slice(&data[1..5]);
+                DetectedCard::ISO14443B { pupi }
+            },
+            // SENS_RES and JEWELID
+            PollTarget::Jewel if data.len() >= 6 => {
+                let mut id = [0u8; 4];
+                id.copy_from_slice(&data[2..6]);
+                DetectedCard::Jewel { id }
+            },
+            target => DetectedCard::Other { target, data: data.to_vec() },
+        }
+    }
+
+    /// Converts the tag to ISO14443A tag if it is one, returns it back otherwise.
+    ///
+    /// The converted tag can't be used to get the next tag.
+    pub fn into_iso14443a(self) -> Result<Tag<'p, 'r, ISO14443A<'r>, P>, Self> {
+        self.into_protocol::<ISO14443AListOptions>()
+    }
+
+    /// Converts the tag to the response type of `Pr` if the tag has its type, returns it back otherwise.
+    ///
+    /// Target data of InAutoPoll have the same layout as InListPassiveTarget response.
+    /// The converted tag can't be used to get the next tag.
+    pub fn into_protocol<Pr: Protocol<'r>>(self) -> Result<Tag<'p, 'r, Pr::Response, P>, Self> {
+        if !Pr::is_target(self.target()) {
+            return Err(self);
+        }
+
+        let len = self.response.len();
+        let data = self.response.into_buf();
+        Ok(Tag {
+            response: Pr::Response::new(&data[2..::core::cmp::min(len, data.len())]),
+            pn532: self.pn532,
+            last: true,
+            _phantom: Default::default(),
+        })
+    }
+}
+
+/// Request code of FeliCa polling, selecting additional information returned by the card.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FeliCaRequestCode {
+    /// No additional information.
+    None,
+    /// The card also returns system code.
+    SystemCode,
+    /// The card also returns supported communication speeds.
+    CommunicationPerformance,
+}
+
+impl FeliCaRequestCode {
+    pub fn code(self) -> u8 {
+        match self {
+            FeliCaRequestCode::None => 0x00,
+            FeliCaRequestCode::SystemCode => 0x01,
+            FeliCaRequestCode::CommunicationPerformance => 0x02,
+        }
+    }
+}
+
+/// Number of time slots in which FeliCa cards may respond to polling.
+///
+/// Each card responds in a randomly chosen slot, so more slots are needed to detect
+/// several cards at once. Time slot number (TSN) sent to the card is the number of slots
+/// minus one, only 0, 1, 3, 7 and 15 are allowed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FeliCaTimeSlots {
+    One,
+    Two,
+    Four,
+    Eight,
+    Sixteen,
+}
+
+impl FeliCaTimeSlots {
+    /// Time slot number (TSN) sent in polling, which is the number of slots minus one.
+    pub fn code(self) -> u8 {
+        match self {
+            FeliCaTimeSlots::One => 0x00,
+            FeliCaTimeSlots::Two => 0x01,
+            FeliCaTimeSlots::Four => 0x03,
+            FeliCaTimeSlots::Eight => 0x07,
+            FeliCaTimeSlots::Sixteen => 0x0F,
+        }
+    }
+
+    pub fn count(self) -> usize {
+        self.code() as usize + 1
+    }
+}
+
+impl ::core::convert::TryFrom<u8> for FeliCaTimeSlots {
+    type Error = InvalidTimeSlotNumber;
+
+    /// Converts time slot number (TSN), which is the number of slots minus one.
+    fn try_from(tsn: u8) -> Result<Self, Self::Error> {
+        match tsn {
+            0x00 => Ok(FeliCaTimeSlots::One),
+            0x01 => Ok(FeliCaTimeSlots::Two),
+            0x03 => Ok(FeliCaTimeSlots::Four),
+            0x07 => Ok(FeliCaTimeSlots::Eight),
+            0x0F => Ok(FeliCaTimeSlots::Sixteen),
+            _ => Err(InvalidTimeSlotNumber(tsn)),
+        }
+    }
+}
+
+/// Payload of FeliCa polling, which is sent as initiator data of InListPassiveTarget.
+///
+/// By default any card is polled (wildcard system code 0xFFFF) in single time slot.
+/// Cards of specific system can be selected using `system_code`, e.g. 0x0003 for Suica.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FeliCaPolling {
+    pub system_code: u16,
+    pub request_code: FeliCaRequestCode,
+    pub time_slots: FeliCaTimeSlots,
+}
+
+impl FeliCaPolling {
+    /// System code matching any card.
+    pub const ANY_SYSTEM: u16 = 0xFFFF;
+
+    pub fn new() -> Self {
+        FeliCaPolling {
+            system_code: FeliCaPolling::ANY_SYSTEM,
+            request_code: FeliCaRequestCode::None,
+            time_slots: FeliCaTimeSlots::One,
+        }
+    }
+
+    pub fn system_code(mut self, system_code: u16) -> Self {
+        self.system_code = system_code;
+        self
+    }
+
+    pub fn request_code(mut self, request_code: FeliCaRequestCode) -> Self {
+        self.request_code = request_code;
+        self
+    }
+
+    pub fn time_slots(mut self, time_slots: FeliCaTimeSlots) -> Self {
+        self.time_slots = time_slots;
+        self
+    }
+
+    /// Encodes the polling command: command code 0x00, system code, request code and TSN.
+    pub fn to_bytes(&self) -> [u8; 5] {
+        [0x00, (self.system_code >> 8) as u8, self.system_code as u8, self.request_code.code(), self.time_slots.code()]
+    }
+}
+
+impl Default for FeliCaPolling {
+    fn default() -> Self {
+        FeliCaPolling::new()
+    }
+}
+
+/// Baud rate of FeliCa cards to list.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FeliCaBaudrate {
+    Br212,
+    Br424,
+}
+
+/// Lists FeliCa cards responding to `polling`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FeliCaListOptions {
+    pub limit: TagNumLimit,
+    pub baudrate: FeliCaBaudrate,
+    /// Cards answering in the same time slot collide, so listing two cards
+    /// needs more than `FeliCaTimeSlots::One`.
+    pub polling: FeliCaPolling,
+}
+
+impl<'r> Protocol<'r> for FeliCaListOptions {
+    type Response = FeliCa<'r>;
+
+    fn baud_type(&self) -> BaudType {
+        match self.baudrate {
+            FeliCaBaudrate::Br212 => BaudType::FeliCa212,
+            FeliCaBaudrate::Br424 => BaudType::FeliCa424,
+        }
+    }
+
+    fn is_target(target: PollTarget) -> bool {
+        target == PollTarget::FeliCa212 || target == PollTarget::FeliCa424
+    }
+
+    fn max_tags(&self) -> u8 {
+        self.limit.into()
+    }
+
+    fn fill_initiator_data(&self, buf: &mut [u8]) -> usize {
+        buf[..5].copy_from_slice(&self.polling.to_bytes());
+        5
+    }
+}
+
+/// FeliCa card.
+pub struct FeliCa<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FeliCa<'a> {
+    // Polling response follows Tg and its length byte, which counts itself.
+    fn pol_res(&self) -> &'a [u8] {
+        let len = self.data.get(1).map_or(0, |len| *len as usize);
+        self.data.get(1..(1 + len)).unwrap_or(&[])
+    }
+}
+
+impl<'a> TagResponse<'a> for FeliCa<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        FeliCa {
+            data: buf,
+        }
+    }
+
+    // Tg and POL_RES
+    fn len(&self) -> usize {
+        1 + self.data.get(1).map_or(0, |len| *len as usize)
+    }
+
+    fn buf(&self) -> &[u8] {
+        self.data
+    }
+
+    fn into_buf(self) -> &'a [u8] {
+        self.data
+    }
+
+    fn uid(&self) -> Option<&[u8]> {
+        self.pol_res().get(2..10)
+    }
+}
+
+impl<'r, 'p, P: PN532Transceive> Tag<'p, 'r, FeliCa<'r>, P> {
+    /// Returns IDm (NFCID2), or empty slice if the response is too short to contain it.
+    pub fn idm(&self) -> &[u8] {
+        self.response.pol_res().get(2..10).unwrap_or(&[])
+    }
+
+    /// Returns PMm, or empty slice if the response is too short to contain it.
+    pub fn pmm(&self) -> &[u8] {
+        self.response.pol_res().get(10..18).unwrap_or(&[])
+    }
+
+    /// Returns the system code, present only if it was requested by `FeliCaRequestCode::SystemCode`.
+    pub fn system_code(&self) -> Option<u16> {
+        let bytes = self.response.pol_res().get(18..20)?;
+        Some(((bytes[0] as u16) << 8) | (bytes[1] as u16))
+    }
+}
+
+/*
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PollingMethod {
+    Probabilistic,
+    Timeslot,
+}
+
+impl PollingMethod {
+    fn code(self) -> u8 {
+        match self {
+            PollingMethod::Probabilistic => 0x01,
+            PollingMethod::Timeslot => 0x00,
+        }
+    }
+}
+
+impl Default for PollingMethod {
+    fn default() -> Self {
+        PollingMethod::Timeslot
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ISO14443BListOptions {
+    pub limit: TagNumLimit,
+    pub afi: u8,
+    pub polling_method: Option<PollingMethod>,
+}
+
+impl TagListOptions for ISO14443BListOptions {
+    fn fill_buf(&self, buf: &mut [u8]) -> usize {
+        buf[0] = self.limit.into();
+        buf[1] = BaudType::ISO14443B106.code();
+        buf[2] = self.afi;
+        self.polling_method.map_or(3, |method| {
+            buf[3] = method.code();
+            4
+        })
+    }
+}
+
+*/
+
+#[cfg(test)]
+mod test {
+    use super::{CascadeLevel, Uid, ISO14443A, ISO14443AListOptions, TagListOptions, TagNumLimit, TagBuffer, Tags, PN532Transceive, AccessBits, SectorTrailer, FeliCaPolling, FeliCaRequestCode, FeliCaTimeSlots};
+
+    struct NoTransceive;
+
+    impl PN532Transceive for NoTransceive {
+        type TransceiveError = ();
+
+        fn transceive(&mut self, _: u8, _: &[u8], _: &mut [u8]) -> Result<usize, ()> {
+            Err(())
+        }
+
+        fn transceive_fragmented(&mut self, _: u8, _: usize, _: &[u8], _: &mut [u8]) -> Result<usize, ()> {
+            Err(())
+        }
+
+        fn max_frame_size(&self) -> usize {
+            252
+        }
+
+        fn halt_iso14443a(&mut self) -> Result<(), ()> {
+            Err(())
+        }
+
+        fn target_status(&mut self, _: u8) -> Result<Option<::device::TargetStatus>, ()> {
+            Err(())
+        }
+
+        fn set_baudrate(&mut self, _: u8, _: ::device::Baudrate) -> Result<bool, ()> {
+            Err(())
+        }
+    }
+
+    // Fills tag buffer with InListPassiveTarget response
+    fn tag_buf(response: &[u8]) -> TagBuffer {
+        let mut buf = TagBuffer::new();
+        buf.buf[..response.len()].copy_from_slice(response);
+        buf.len = response.len();
+        buf
+    }
+
+    fn fill_a(uid: &[u8], buf: &mut [u8]) -> usize {
+        let options = ISO14443AListOptions {
+            limit: TagNumLimit::One,
+            uid: Some(Uid::new(uid).unwrap()),
+            wake_up: false,
+        };
+        TagListOptions::fill_buf(&options, buf)
+    }
+
+    #[test]
+    fn iso14443a_limit() {
+        for &(limit, code) in &[(TagNumLimit::One, 1), (TagNumLimit::Two, 2)] {
+            let options = ISO14443AListOptions { limit, uid: None, wake_up: false };
+            let mut buf = [0u8; 2];
+            assert_eq!(TagListOptions::fill_buf(&options, &mut buf), 2);
+            assert_eq!(buf, [code, 0x00]);
+        }
+    }
+
+    #[test]
+    fn auto_poll_into_protocol() {
+        use super::{AutoPolled, Jewel, JewelTagListOptions, ISO14443AListOptions, TagResponse};
+
+        let buf = tag_buf(&[0x61, 0x01, 0x04, 0x07, 0x01, 0x0C, 0x00, 0xB2, 0x56, 0x7E, 0x02]);
+        let mut pn532 = NoTransceive;
+        let tag = unsafe { Tags::<AutoPolled, _>::new(&buf, &mut pn532) }.first();
+        let tag = tag.into_protocol::<ISO14443AListOptions>().map(|_| ()).unwrap_err();
+        let tag = tag.into_protocol::<JewelTagListOptions>().map_err(|_| ()).unwrap();
+        assert_eq!(tag.id(), [0xB2, 0x56, 0x7E, 0x02]);
+
+        let buf = tag_buf(&[0x61, 0x01, 0x04, 0x03, 0x01, 0x0C, 0x00]);
+        let tag = unsafe { Tags::<AutoPolled, _>::new(&buf, &mut pn532) }.first();
+        let tag = tag.into_protocol::<JewelTagListOptions>().map_err(|_| ()).unwrap();
+        assert_eq!(tag.sens_res(), 0x0C00);
+        assert_eq!(tag.id(), &[] as &[u8]);
+        assert_eq!(TagResponse::uid(&Jewel::new(&[0x01, 0x0C, 0x00])), None);
+    }
+
+    #[test]
+    fn jewel_limit() {
+        use ::core::convert::TryFrom;
+        use ::error::UnsupportedTagLimit;
+        use super::{Jewel, JewelTagListOptions};
+
+        assert_eq!(JewelTagListOptions::try_from(TagNumLimit::Two).map(|_| ()), Err(UnsupportedTagLimit(2)));
+        let options = JewelTagListOptions::try_from(TagNumLimit::One).unwrap();
+        let mut buf = [0u8; 2];
+        assert_eq!(TagListOptions::fill_buf(&options, &mut buf), 2);
+        assert_eq!(buf, [0x01, 0x04]);
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x0C, 0x00, 0xB2, 0x56, 0x7E, 0x02]);
+        let mut pn532 = NoTransceive;
+        let tag = unsafe { Tags::<Jewel, _>::new(&buf, &mut pn532) }.first();
+        assert_eq!(tag.sens_res(), 0x0C00);
+        assert_eq!(tag.id(), [0xB2, 0x56, 0x7E, 0x02]);
+    }
+
+    #[test]
+    fn tag_infos() {
+        let buf = tag_buf(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x44, 0x20, 0x04, 0x01, 0x02, 0x03, 0x04, 0x01]);
+        let mut pn532 = NoTransceive;
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) };
+
+        let infos = tags.infos().collect::<Vec<_>>();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].id(), [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(infos[1].sens_res(), 0x0044);
+        assert!(infos[1].is_iso14443_4());
+
+        let tag = tags.nth(1).unwrap();
+        assert_eq!(tag.id(), infos[1].id());
+
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) };
+        assert!(tags.nth(2).is_none());
+    }
+
+    #[test]
+    fn partial_iso14443a() {
+        use super::TagResponse;
+
+        // Tg, SENS_RES and SEL_RES, but no UID nor ATS
+        let tag = ISO14443A::new(&[0x01, 0x00, 0x44, 0x20]);
+        assert_eq!(tag.checked_sens_res(), Some(0x0044));
+        assert_eq!(tag.checked_sel_res(), Some(0x20));
+        assert_eq!(tag.checked_id(), None);
+        assert_eq!(tag.checked_ats(), None);
+        assert_eq!(tag.id(), &[] as &[u8]);
+        assert_eq!(tag.ats(), &[] as &[u8]);
+        assert_eq!(tag.uid(), None);
+        assert!(!tag.is_uid_complete());
+
+        // UID cut short
+        let tag = ISO14443A::new(&[0x01, 0x00, 0x44, 0x00, 0x07, 0x04, 0xA1]);
+        assert_eq!(tag.checked_id(), None);
+        assert!(!tag.is_uid_complete());
+
+        // Only SENS_RES
+        let tag = ISO14443A::new(&[0x01, 0x00, 0x04]);
+        assert_eq!(tag.checked_sens_res(), Some(0x0004));
+        assert_eq!(tag.checked_sel_res(), None);
+        assert_eq!(tag.sel_res(), 0);
+
+        // Complete UID, ATS missing
+        let tag = ISO14443A::new(&[0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(tag.checked_id(), Some(&[0xDE, 0xAD, 0xBE, 0xEF][..]));
+        assert_eq!(tag.checked_ats(), None);
+
+        // Tags without ISO14443-4 support have no ATS
+        let tag = ISO14443A::new(&[0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(tag.checked_ats(), Some(&[][..]));
+        assert_eq!(tag.len(), 9);
+
+        let next = ISO14443A::new(&[0x01, 0x00, 0x04, 0x08, 0x04, 0xDE]).next();
+        assert_eq!(next.checked_sens_res(), None);
+    }
+
+    #[test]
+    fn tag_summaries() {
+        use super::TagSummary;
+
+        let buf = tag_buf(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x44, 0x20, 0x04, 0x01, 0x02, 0x03, 0x04, 0x01]);
+        let mut pn532 = NoTransceive;
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) };
+        let summaries = tags.summaries();
+        assert_eq!(summaries[0], Some(TagSummary { atqa: 0x0004, sak: 0x08, uid: &[0xDE, 0xAD, 0xBE, 0xEF] }));
+        assert_eq!(summaries[1], Some(TagSummary { atqa: 0x0044, sak: 0x20, uid: &[0x01, 0x02, 0x03, 0x04] }));
+        assert_eq!(tags.nth(1).unwrap().id(), summaries[1].unwrap().uid);
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) };
+        assert_eq!(tags.summaries()[1], None);
+    }
+
+    #[test]
+    fn access_bits() {
+        assert_eq!(AccessBits::TRANSPORT.to_bytes(), [0xFF, 0x07, 0x80]);
+        assert_eq!(AccessBits::from_bytes([0xFF, 0x07, 0x80]), Some(AccessBits::TRANSPORT));
+        assert_eq!(AccessBits::from_bytes([0xFF, 0x07, 0x81]), None);
+        assert_eq!(AccessBits::new([0b1000, 0, 0, 0]), None);
+
+        let bits = AccessBits::new([0b100, 0b010, 0b001, 0b011]).unwrap();
+        assert_eq!(AccessBits::from_bytes(bits.to_bytes()), Some(bits));
+        assert_eq!(bits.condition(3), 0b011);
+
+        let trailer = SectorTrailer { key_a: [0xA0; 6], access_bits: AccessBits::TRANSPORT, gpb: 0x69, key_b: [0xB0; 6] };
+        assert_eq!(trailer.to_bytes(), [0xA0, 0xA0, 0xA0, 0xA0, 0xA0, 0xA0, 0xFF, 0x07, 0x80, 0x69, 0xB0, 0xB0, 0xB0, 0xB0, 0xB0, 0xB0]);
+        assert_eq!(SectorTrailer::block(1), Some(7));
+        assert_eq!(SectorTrailer::block(32), Some(143));
+        assert_eq!(SectorTrailer::block(39), Some(255));
+        assert_eq!(SectorTrailer::block(40), None);
+    }
+
+    #[test]
+    fn felica_polling() {
+        assert_eq!(FeliCaPolling::default().to_bytes(), [0x00, 0xFF, 0xFF, 0x00, 0x00]);
+        let polling = FeliCaPolling::new()
+            .system_code(0x0003)
+            .request_code(FeliCaRequestCode::SystemCode)
+            .time_slots(FeliCaTimeSlots::Four);
+        assert_eq!(polling.to_bytes(), [0x00, 0x00, 0x03, 0x01, 0x03]);
+    }
+
+    #[test]
+    fn felica_list() {
+        use super::{FeliCa, FeliCaBaudrate, FeliCaListOptions, AutoPolled};
+
+        let options = FeliCaListOptions {
+            limit: TagNumLimit::One,
+            baudrate: FeliCaBaudrate::Br424,
+            polling: FeliCaPolling::new().system_code(0x0003).request_code(FeliCaRequestCode::SystemCode),
+        };
+        let mut buf = [0u8; 8];
+        assert_eq!(TagListOptions::fill_buf(&options, &mut buf), 7);
+        assert_eq!(buf[..7], [0x01, 0x02, 0x00, 0x00, 0x03, 0x01, 0x00]);
+
+        let buf = tag_buf(&[0x4B, 0x02,
+            0x01, 0x14, 0x01, 1, 2, 3, 4, 5, 6, 7, 8, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x00, 0x03,
+            0x02, 0x12, 0x01, 9, 10, 11, 12, 13, 14, 15, 16, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut pn532 = NoTransceive;
+        let tags = unsafe { Tags::<FeliCa, _>::new(&buf, &mut pn532) };
+        let first = tags.first();
+        assert_eq!(first.idm(), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(first.pmm(), [0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]);
+        assert_eq!(first.system_code(), Some(0x0003));
+        let second = first.next().unwrap();
+        assert_eq!(second.idm(), [9, 10, 11, 12, 13, 14, 15, 16]);
+        assert_eq!(second.system_code(), None);
+
+        let buf = tag_buf(&[0x61, 0x01, 0x11, 0x13, 0x01, 0x12, 0x01, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let tag = unsafe { Tags::<AutoPolled, _>::new(&buf, &mut pn532) }.first();
+        let tag = tag.into_protocol::<FeliCaListOptions>().map_err(|_| ()).unwrap();
+        assert_eq!(tag.idm(), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn felica_time_slots() {
+        use ::core::convert::TryFrom;
+        use ::error::InvalidTimeSlotNumber;
+        use super::{FeliCaBaudrate, FeliCaListOptions};
+
+        for &slots in &[FeliCaTimeSlots::One, FeliCaTimeSlots::Two, FeliCaTimeSlots::Four, FeliCaTimeSlots::Eight, FeliCaTimeSlots::Sixteen] {
+            assert_eq!(FeliCaTimeSlots::try_from(slots.code()), Ok(slots));
+        }
+        assert_eq!(FeliCaTimeSlots::Eight.count(), 8);
+        assert_eq!(FeliCaTimeSlots::try_from(2), Err(InvalidTimeSlotNumber(2)));
+        assert_eq!(FeliCaTimeSlots::try_from(16), Err(InvalidTimeSlotNumber(16)));
+
+        let options = FeliCaListOptions {
+            limit: TagNumLimit::Two,
+            baudrate: FeliCaBaudrate::Br212,
+            polling: FeliCaPolling::new().time_slots(FeliCaTimeSlots::try_from(0x07).unwrap()),
+        };
+        let mut buf = [0u8; 8];
+        assert_eq!(TagListOptions::fill_buf(&options, &mut buf), 7);
+        assert_eq!(buf[..7], [0x02, 0x01, 0x00, 0xFF, 0xFF, 0x00, 0x07]);
+    }
+
+    #[test]
+    fn deduplicate() {
+        let buf = tag_buf(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        let mut pn532 = NoTransceive;
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.deduplicate();
+        assert_eq!(tags.count(), 1);
+        assert!(tags.first().next().is_none());
+
+        let buf = tag_buf(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x44, 0x20, 0x04, 0x01, 0x02, 0x03, 0x04, 0x01]);
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.deduplicate();
+        assert_eq!(tags.count(), 2);
+
+        // MIFARE Classic and Ultralight, neither of them sends ATS
+        let buf = tag_buf(&[0x4B, 0x02,
+            0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x02, 0x00, 0x44, 0x00, 0x07, 0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.deduplicate();
+        assert_eq!(tags.count(), 2);
+        assert_eq!(tags.first().next().unwrap().id(), [0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+    }
+
+    #[test]
+    fn auto_poll() {
+        use super::{AutoPollOptions, AutoPolled, PollTarget};
+
+        let options = AutoPollOptions { poll_count: 1, period: 2, targets: &[PollTarget::ISO14443A, PollTarget::FeliCa212] };
+        let mut buf = [0u8; 8];
+        assert_eq!(TagListOptions::fill_buf(&options, &mut buf), 4);
+        assert_eq!(buf[..4], [0x01, 0x02, 0x00, 0x01]);
+        assert_eq!(TagListOptions::command_code(&options), 0x60);
+
+        let buf = tag_buf(&[0x61, 0x02,
+            0x10, 0x09, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x11, 0x13, 0x02, 0x12, 0x01, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut pn532 = NoTransceive;
+        let tags = unsafe { Tags::<AutoPolled, _>::new(&buf, &mut pn532) };
+        assert_eq!(tags.count(), 2);
+
+        let first = tags.first();
+        assert_eq!(first.target(), PollTarget::ISO14443A);
+        let second = first.next().unwrap();
+        assert_eq!(second.target(), PollTarget::FeliCa212);
+        assert_eq!(second.target_data()[..3], [0x02, 0x12, 0x01]);
+        let second = second.into_iso14443a().map(|_| ()).unwrap_err();
+        assert!(second.next().is_none());
+
+        let tags = unsafe { Tags::<AutoPolled, _>::new(&buf, &mut pn532) };
+        let tag = tags.first().into_iso14443a().map_err(|_| ()).unwrap();
+        assert_eq!(tag.id(), [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(tag.sel_res(), 0x08);
+
+        // ATS is missing, it must not be taken from the following target
+        let buf = tag_buf(&[0x61, 0x02,
+            0x10, 0x08, 0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+            0x10, 0x09, 0x02, 0x00, 0x04, 0x08, 0x04, 0x01, 0x02, 0x03, 0x04]);
+        let tags = unsafe { Tags::<AutoPolled, _>::new(&buf, &mut pn532) };
+        let tag = tags.first().into_iso14443a().map_err(|_| ()).unwrap();
+        assert_eq!(tag.ats_len(), 0);
+        assert_eq!(tag.ats(), &[] as &[u8]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tag_snapshot() {
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x03, 0x78, 0x80]);
+        let mut pn532 = NoTransceive;
+        let tags = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) };
+        assert_eq!(tags.raw_len(), 14);
+        let snapshot = tags.first().to_owned();
+
+        assert_eq!(snapshot.sens_res, 0x0004);
+        assert_eq!(snapshot.sel_res, 0x20);
+        assert_eq!(snapshot.id, [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(snapshot.ats, [0x78, 0x80]);
+        assert_eq!(snapshot.clone(), snapshot);
+    }
+
+    #[test]
+    fn sak_bits() {
+        let mut pn532 = NoTransceive;
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x20, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x01]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(tag.is_iso14443_4());
+        assert!(!tag.supports_nfc_dep());
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x00]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(!tag.is_iso14443_4());
+        assert!(!tag.supports_nfc_dep());
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x60, 0x04, 0xDE, 0xAD, 0xBE, 0xEF, 0x01]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(tag.is_iso14443_4());
+        assert!(tag.supports_nfc_dep());
+    }
+
+    #[test]
+    fn uid_complete() {
+        let mut pn532 = NoTransceive;
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x04, 0x08, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(tag.is_uid_complete());
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x44, 0x00, 0x07, 0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x01]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert_eq!(tag.id(), &[0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+        assert!(tag.is_uid_complete());
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x84, 0x20, 0x0A, 0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0x01]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert_eq!(tag.id_len(), 10);
+        assert!(tag.is_uid_complete());
+
+        // Cascade bit set, UID of first cascade level only
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x44, 0x04, 0x04, 0x88, 0x04, 0x11, 0x22]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(!tag.is_uid_complete());
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x44, 0x00, 0x04, 0x88, 0x04, 0x11, 0x22]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(!tag.is_uid_complete());
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x00, 0x44, 0x00, 0x05, 0x04, 0x11, 0x22, 0x33, 0x44]);
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert!(!tag.is_uid_complete());
+    }
+
+    #[test]
+    fn ats_defaults() {
+        use super::Ats;
+
+        let ats = Ats::new(&[]);
+        assert_eq!(ats.tl(), 1);
+        assert_eq!(ats.t0(), None);
+        assert_eq!(ats.ta(), None);
+        assert_eq!(ats.max_frame_size(), 32);
+        assert_eq!(ats.fwi(), 4);
+        assert_eq!(ats.sfgi(), 0);
+        assert_eq!(ats.frame_waiting_time().as_micros(), 4833);
+    }
+
+    #[test]
+    fn ats_interface_bytes() {
+        use super::Ats;
+
+        // DESFire EV1
+        let ats = Ats::new(&[0x75, 0x77, 0x81, 0x02, 0x80]);
+        assert_eq!(ats.tl(), 6);
+        assert_eq!(ats.ta(), Some(0x77));
+        assert_eq!(ats.tb(), Some(0x81));
+        assert_eq!(ats.tc(), Some(0x02));
+        assert_eq!(ats.max_frame_size(), 64);
+        assert_eq!(ats.fwi(), 8);
+        assert_eq!(ats.sfgi(), 1);
+        assert_eq!(ats.frame_waiting_time().as_micros(), 77328);
+
+        // Only TB present
+        let ats = Ats::new(&[0x28, 0xF1]);
+        assert_eq!(ats.ta(), None);
+        assert_eq!(ats.tb(), Some(0xF1));
+        assert_eq!(ats.tc(), None);
+        assert_eq!(ats.max_frame_size(), 256);
+        assert_eq!(ats.fwi(), 4);
+
+        // TC announced but missing
+        let ats = Ats::new(&[0x40]);
+        assert_eq!(ats.tc(), None);
+    }
+
+    #[test]
+    fn historical_bytes() {
+        use super::Ats;
+
+        assert_eq!(Ats::new(&[0x75, 0x77, 0x81, 0x02, 0x80]).historical_bytes(), &[0x80]);
+        // JavaCard with "JCOP" in historical bytes
+        assert_eq!(Ats::new(&[0x78, 0x80, 0x70, 0x02, 0x4A, 0x43, 0x4F, 0x50]).historical_bytes(), b"JCOP");
+        assert_eq!(Ats::new(&[0x75, 0x77, 0x81, 0x02]).historical_bytes(), &[]);
+        assert_eq!(Ats::new(&[0x05]).historical_bytes(), &[]);
+        assert_eq!(Ats::new(&[0x02, 0xC1]).historical_bytes(), &[0xC1]);
+        assert_eq!(Ats::new(&[]).historical_bytes(), &[]);
+        // Announced interface bytes missing
+        assert_eq!(Ats::new(&[0x70, 0x77]).historical_bytes(), &[]);
+
+        let buf = tag_buf(&[0x4B, 0x01, 0x01, 0x03, 0x44, 0x20, 0x07, 0x04, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x06, 0x75, 0x77, 0x81, 0x02, 0x80]);
+        let mut pn532 = NoTransceive;
+        let tag = unsafe { Tags::<ISO14443A, _>::new(&buf, &mut pn532) }.first();
+        assert_eq!(tag.historical_bytes(), &[0x80]);
+    }
+
+    #[test]
+    fn uid_invalid_len() {
+        for len in &[0, 1, 3, 5, 6, 8, 9, 11, 12] {
+            let uid = [0u8; 12];
+            assert_eq!(Uid::new(&uid[..*len]).unwrap_err().0, *len);
+        }
+    }
+
+    #[test]
+    fn uid_single() {
+        let uid = [1, 2, 3, 4];
+        let mut buf = [0u8; 32];
+        assert_eq!(Uid::new(&uid).unwrap().cascade_level(), CascadeLevel::Single);
+        assert_eq!(fill_a(&uid, &mut buf), 6);
+        assert_eq!(buf[..6], [0x01, 0x00, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn uid_double() {
+        let uid = [1, 2, 3, 4, 5, 6, 7];
+        let mut buf = [0u8; 32];
+        assert_eq!(Uid::new(&uid).unwrap().cascade_level(), CascadeLevel::Double);
+        assert_eq!(fill_a(&uid, &mut buf), 10);
+        assert_eq!(buf[..10], [0x01, 0x00, 0x88, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn uid_triple() {
+        let uid = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let mut buf = [0u8; 32];
+        assert_eq!(Uid::new(&uid).unwrap().cascade_level(), CascadeLevel::Triple);
+        assert_eq!(fill_a(&uid, &mut buf), 14);
+        assert_eq!(buf[..14], [0x01, 0x00, 0x88, 1, 2, 3, 0x88, 4, 5, 6, 7, 8, 9, 10]);
+    }
+}
@@ -1,60 +1,71 @@
 //! Contains error types and corresponding impls.
 
-use ::std::error;
-use ::std::fmt;
+use ::core::error;
+use ::core::fmt;
+use ::core::time::Duration;
+use ::device::Frame;
 
 /// Error type used for operations that may timeout.
+///
+/// `D` is the duration type of the timer used for waiting.
 #[derive(Debug)]
-pub enum WaitError<E: error::Error> {
+pub enum WaitError<E: error::Error, D = Duration> {
     /// Some other error occured.
     OtherError(E),
 
-    /// Operation timed out.
-    Timeout,
+    /// Operation timed out after waiting for given time.
+    Timeout(D),
+
+    /// Waiting was cancelled using `CancelToken`.
+    Cancelled,
 }
 
-impl<E: error::Error> WaitError<E> {
-    pub fn map<E2: error::Error, F: FnOnce(E) -> E2>(self, f: F) -> WaitError<E2> {
+impl<E: error::Error, D> WaitError<E, D> {
+    pub fn map<E2: error::Error, F: FnOnce(E) -> E2>(self, f: F) -> WaitError<E2, D> {
         match self {
             WaitError::OtherError(e) => WaitError::OtherError(f(e)),
-            WaitError::Timeout => WaitError::Timeout,
+            WaitError::Timeout(elapsed) => WaitError::Timeout(elapsed),
+            WaitError::Cancelled => WaitError::Cancelled,
         }
     }
 }
 
-impl<E: error::Error> fmt::Display for WaitError<E> {
+impl<E: error::Error, D: fmt::Debug> fmt::Display for WaitError<E, D> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             WaitError::OtherError(ref e) => write!(f, "error while waiting for data: {}", e),
-            WaitError::Timeout => write!(f, "operation timed out"),
+            WaitError::Timeout(ref elapsed) => write!(f, "operation timed out after {:?}", elapsed),
+            WaitError::Cancelled => write!(f, "operation was cancelled"),
         }
     }
 }
 
-impl <E: error::Error> error::Error for WaitError<E> {
+impl <E: error::Error, D: fmt::Debug> error::Error for WaitError<E, D> {
     fn description(&self) -> &str {
         match *self {
-            WaitError::OtherError(ref e) => e.description(),
-            WaitError::Timeout => "operation timed out",
+            WaitError::OtherError(_) => "error while waiting for data",
+            WaitError::Timeout(_) => "operation timed out",
+            WaitError::Cancelled => "operation was cancelled",
         }
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             WaitError::OtherError(ref e) => Some(e),
-            WaitError::Timeout => None,
+            WaitError::Timeout(_) => None,
+            WaitError::Cancelled => None,
         }
     }
 }
 
-impl<E: error::Error> From<E> for WaitError<E> {
+impl<E: error::Error, D> From<E> for WaitError<E, D> {
     fn from(e: E) -> Self {
         WaitError::OtherError(e)
     }
 }
 
 /// Type returned from functions which may timeout.
-pub type WaitResult<T, E> = Result<T, WaitError<E>>;
+pub type WaitResult<T, E, D = Duration> = Result<T, WaitError<E, D>>;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ChecksumType {
@@ -64,15 +75,21 @@ pub enum ChecksumType {
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum DataError {
-    InvalidChecksum(ChecksumType),
+    /// Checksum byte `received` in the frame doesn't match the `expected` one, calculated from the frame.
+    InvalidChecksum { which: ChecksumType, received: u8, expected: u8 },
     InvalidByte(u8, &'static str),
+    /// Frame declares length `len`, but at most `max` fits into the bytes which were read.
+    ///
+    /// Either the length byte is corrupt or the buffer used for reading is too small.
+    LengthOutOfRange { len: u8, max: usize },
 }
 
 impl fmt::Display for DataError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            DataError::InvalidChecksum(ref ct) => write!(f, "packet {} has invalid checksum", if *ct == ChecksumType::Length { "length" } else { "data" }),
+            DataError::InvalidChecksum { which, received, expected } => write!(f, "packet {} has invalid checksum (received 0x{:02X}, expected 0x{:02X})", if which == ChecksumType::Length { "length" } else { "data" }, received, expected),
             DataError::InvalidByte(ref b, ref expected) => write!(f, "invalid byte ({}) encountered. Expected {}.", b, expected),
+            DataError::LengthOutOfRange { len, max } => write!(f, "frame length {} exceeds {} bytes available", len, max),
         }
     }
 }
@@ -82,6 +99,20 @@ pub enum RecvError<E: error::Error> {
     ReadError(E),
     InvalidData(DataError),
     UnexpectedEnd,
+    /// Nothing was received at all, unlike `UnexpectedEnd` which means the frame was truncated.
+    NoData,
+    /// Received valid frame of different kind than expected.
+    UnexpectedFrame(Frame),
+    /// Data of the frame didn't fit into the buffer, only `copied` bytes out of `total` were stored.
+    Truncated { copied: usize, total: usize },
+    /// No complete frame was found within given number of bytes.
+    ScanLimitExceeded(usize),
+    /// PN532 acknowledged the command, but then replied with error frame instead of the response.
+    ErrorFrame,
+    /// The tag kept announcing more data after given number of GET RESPONSE commands.
+    ChainTooLong(usize),
+    /// PN532 kept sending frames, given number of them was discarded without it going quiet.
+    StillStreaming(usize),
 }
 
 impl<E: error::Error> From<DataError> for RecvError<E> {
@@ -96,6 +127,13 @@ impl<E: error::Error> fmt::Display for RecvError<E> {
             RecvError::ReadError(ref e) => write!(f, "read error: {}", e),
             RecvError::InvalidData(ref d) => write!(f, "error parsing packet: {}", d),
             RecvError::UnexpectedEnd => write!(f, "received message is too short"),
+            RecvError::NoData => write!(f, "no data received"),
+            RecvError::UnexpectedFrame(ref frame) => write!(f, "received unexpected frame: {:?}", frame),
+            RecvError::Truncated { copied, total } => write!(f, "buffer too small, only {} of {} bytes of data copied", copied, total),
+            RecvError::ScanLimitExceeded(limit) => write!(f, "no frame found within {} bytes", limit),
+            RecvError::ErrorFrame => write!(f, "PN532 replied with error frame"),
+            RecvError::ChainTooLong(rounds) => write!(f, "response chain didn't end after {} GET RESPONSE commands", rounds),
+            RecvError::StillStreaming(frames) => write!(f, "PN532 still sending after {} discarded frames", frames),
         }
     }
 }
@@ -105,11 +143,18 @@ impl<E: error::Error> error::Error for RecvError<E> {
         "error receiving message from PN532"
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             RecvError::ReadError(ref e) => Some(e),
             RecvError::InvalidData(_) => None,
             RecvError::UnexpectedEnd => None,
+            RecvError::NoData => None,
+            RecvError::UnexpectedFrame(_) => None,
+            RecvError::Truncated { .. } => None,
+            RecvError::ScanLimitExceeded(_) => None,
+            RecvError::ErrorFrame => None,
+            RecvError::ChainTooLong(_) => None,
+            RecvError::StillStreaming(_) => None,
         }
     }
 }
@@ -118,6 +163,8 @@ impl<E: error::Error> error::Error for RecvError<E> {
 pub enum SendError<E: error::Error> {
     WriteError(E),
     TooMuchData(usize),
+    /// Sending `required` bytes was requested, but only `available` bytes were given.
+    NotEnoughData { required: usize, available: usize },
 }
 
 impl<E: error::Error> From<E> for SendError<E> {
@@ -131,6 +178,7 @@ impl<E: error::Error> fmt::Display for SendError<E> {
         match *self {
             SendError::WriteError(ref e) => write!(f, "{}", e),
             SendError::TooMuchData(l) => write!(f, "tried to write {} bytes of data but writing more than 254 bytes is not supported", l),
+            SendError::NotEnoughData { required, available } => write!(f, "tried to write {} bytes of data but only {} bytes were given", required, available),
         }
     }
 }
@@ -140,18 +188,167 @@ impl<E: error::Error> error::Error for SendError<E> {
         "sending message to PN532 failed"
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             SendError::WriteError(ref e) => Some(e),
             SendError::TooMuchData(_) => None,
+            SendError::NotEnoughData { .. } => None,
+        }
+    }
+}
+
+/// Error reported by PN532 in status byte of a response.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Pn532Status {
+    /// The target has not answered.
+    Timeout,
+    /// CRC error detected by CIU.
+    Crc,
+    /// Parity error detected by CIU.
+    Parity,
+    /// Erroneous bit count during anticollision/select (ISO14443-3).
+    BitCount,
+    /// Framing error during MIFARE operation.
+    Framing,
+    /// Abnormal bit-collision during bitwise anticollision at 106 kbps.
+    BitCollision,
+    /// Communication buffer size insufficient.
+    BufferSize,
+    /// RF buffer overflow detected by CIU.
+    RfBufferOverflow,
+    /// RF field has not been switched on in time by the counterpart (active mode).
+    RfFieldTimeout,
+    /// RF protocol error.
+    RfProtocol,
+    /// The antenna drivers have been switched off because of overheating.
+    Temperature,
+    /// Internal buffer overflow.
+    InternalBufferOverflow,
+    /// Invalid parameter (range, format, ...).
+    InvalidParameter,
+    /// DEP protocol: command received in target mode is not supported.
+    DepUnsupportedCommand,
+    /// DEP or ISO14443-4: data format doesn't match the specification.
+    InvalidDataFormat,
+    /// MIFARE authentication error.
+    Authentication,
+    /// ISO14443-3: UID check byte is wrong.
+    UidCheckByte,
+    /// DEP protocol: invalid device state.
+    DepInvalidDeviceState,
+    /// Operation not allowed in this configuration (host controller interface).
+    OperationNotAllowed,
+    /// Command is not acceptable in current context (e.g. no target is selected).
+    NotAcceptable,
+    /// The target has been released by initiator.
+    ///
+    /// Also returned by exchanging data with a tag which isn't active anymore,
+    /// e.g. after it was released. Such tag should be discarded and listed again.
+    TargetReleased,
+    /// ISO14443-3B: ID of the card doesn't match.
+    CardIdMismatch,
+    /// ISO14443-3B: previously activated card has disappeared.
+    CardDisappeared,
+    /// NFCID3 of initiator and target don't match in DEP 212/424 kbps passive.
+    Nfcid3Mismatch,
+    /// Over-current event has been detected.
+    OverCurrent,
+    /// NAD missing in DEP frame.
+    NadMissing,
+    /// Error code not known to this crate.
+    Other(u8),
+}
+
+impl Pn532Status {
+    /// Interprets error code in status byte. Returns `None` if the status signals success.
+    ///
+    /// The upper two bits (NAD and MI flags) are ignored.
+    pub fn from_status_byte(status: u8) -> Option<Self> {
+        use self::Pn532Status::*;
+
+        let status = match status & 0x3F {
+            0x00 => return None,
+            0x01 => Timeout,
+            0x02 => Crc,
+            0x03 => Parity,
+            0x04 => BitCount,
+            0x05 => Framing,
+            0x06 => BitCollision,
+            0x07 => BufferSize,
+            0x09 => RfBufferOverflow,
+            0x0A => RfFieldTimeout,
+            0x0B => RfProtocol,
+            0x0D => Temperature,
+            0x0E => InternalBufferOverflow,
+            0x10 => InvalidParameter,
+            0x12 => DepUnsupportedCommand,
+            0x13 => InvalidDataFormat,
+            0x14 => Authentication,
+            0x23 => UidCheckByte,
+            0x25 => DepInvalidDeviceState,
+            0x26 => OperationNotAllowed,
+            0x27 => NotAcceptable,
+            0x29 => TargetReleased,
+            0x2A => CardIdMismatch,
+            0x2B => CardDisappeared,
+            0x2C => Nfcid3Mismatch,
+            0x2D => OverCurrent,
+            0x2E => NadMissing,
+            other => Other(other),
+        };
+        Some(status)
+    }
+}
+
+impl fmt::Display for Pn532Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Pn532Status::*;
+
+        match *self {
+            Timeout => write!(f, "the target has not answered"),
+            Crc => write!(f, "CRC error"),
+            Parity => write!(f, "parity error"),
+            BitCount => write!(f, "erroneous bit count during anticollision"),
+            Framing => write!(f, "framing error during MIFARE operation"),
+            BitCollision => write!(f, "abnormal bit-collision during anticollision"),
+            BufferSize => write!(f, "communication buffer size insufficient"),
+            RfBufferOverflow => write!(f, "RF buffer overflow"),
+            RfFieldTimeout => write!(f, "RF field has not been switched on in time by the counterpart"),
+            RfProtocol => write!(f, "RF protocol error"),
+            Temperature => write!(f, "overheating"),
+            InternalBufferOverflow => write!(f, "internal buffer overflow"),
+            InvalidParameter => write!(f, "invalid parameter"),
+            DepUnsupportedCommand => write!(f, "DEP command not supported"),
+            InvalidDataFormat => write!(f, "invalid data format"),
+            Authentication => write!(f, "MIFARE authentication error"),
+            UidCheckByte => write!(f, "UID check byte is wrong"),
+            DepInvalidDeviceState => write!(f, "invalid DEP device state"),
+            OperationNotAllowed => write!(f, "operation not allowed in this configuration"),
+            NotAcceptable => write!(f, "command not acceptable in current context"),
+            TargetReleased => write!(f, "the target has been released"),
+            CardIdMismatch => write!(f, "ID of the card doesn't match"),
+            CardDisappeared => write!(f, "the card has disappeared"),
+            Nfcid3Mismatch => write!(f, "NFCID3 mismatch"),
+            OverCurrent => write!(f, "over-current detected"),
+            NadMissing => write!(f, "NAD missing in DEP frame"),
+            Other(code) => write!(f, "unknown error 0x{:02X}", code),
         }
     }
 }
 
+impl error::Error for Pn532Status {
+    fn description(&self) -> &str {
+        "PN532 reported error"
+    }
+}
+
 #[derive(Debug)]
 pub enum CommError<R: error::Error, W: error::Error> {
     SendError(SendError<W>),
     RecvError(RecvError<R>),
+    /// Communication with PN532 succeeded, but it reported an error.
+    Status(Pn532Status),
 }
 
 impl<R: error::Error, W: error::Error> fmt::Display for CommError<R, W> {
@@ -159,6 +356,7 @@ impl<R: error::Error, W: error::Error> fmt::Display for CommError<R, W> {
         match *self {
             CommError::SendError(ref e) => write!(f, "sending: {}", e),
             CommError::RecvError(ref e) => write!(f, "receiving: {}", e),
+            CommError::Status(ref e) => write!(f, "PN532 error: {}", e),
         }
     }
 }
@@ -168,10 +366,11 @@ impl<R: error::Error, W: error::Error> error::Error for CommError<R, W> {
         "communication error"
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             CommError::SendError(ref e) => Some(e),
             CommError::RecvError(ref e) => Some(e),
+            CommError::Status(ref e) => Some(e),
         }
     }
 }
@@ -182,6 +381,12 @@ impl<R: error::Error, W: error::Error> From<SendError<W>> for CommError<R, W> {
     }
 }
 
+impl<R: error::Error, W: error::Error> From<Pn532Status> for CommError<R, W> {
+    fn from(e: Pn532Status) -> Self {
+        CommError::Status(e)
+    }
+}
+
 impl<R: error::Error, W: error::Error> From<RecvError<R>> for CommError<R, W> {
     fn from(e: RecvError<R>) -> Self {
         CommError::RecvError(e)
@@ -189,3 +394,174 @@ impl<R: error::Error, W: error::Error> From<RecvError<R>> for CommError<R, W> {
 }
 
 pub type CommResult<T, R, W> = Result<T, CommError<R, W>>;
+
+/// Converts to `io::Error` of kind `Other` carrying the description of the error.
+#[cfg(feature = "std")]
+impl<R: error::Error, W: error::Error> From<CommError<R, W>> for ::std::io::Error {
+    fn from(e: CommError<R, W>) -> Self {
+        ::std::io::Error::other(e.to_string())
+    }
+}
+
+/// Converts to `io::Error` carrying the description of the error.
+/// Timeout is converted to `TimedOut` kind, other errors to `Other`.
+#[cfg(feature = "std")]
+impl<E: error::Error, D: fmt::Debug> From<WaitError<E, D>> for ::std::io::Error {
+    fn from(e: WaitError<E, D>) -> Self {
+        let kind = match e {
+            WaitError::Timeout(_) => ::std::io::ErrorKind::TimedOut,
+            WaitError::Cancelled => ::std::io::ErrorKind::Interrupted,
+            WaitError::OtherError(_) => ::std::io::ErrorKind::Other,
+        };
+        ::std::io::Error::new(kind, e.to_string())
+    }
+}
+
+/// Returned when UID of ISO14443A tag is not 4, 7 or 10 bytes long.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InvalidUidLength(pub usize);
+
+impl fmt::Display for InvalidUidLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid UID length {}, expected 4, 7 or 10 bytes", self.0)
+    }
+}
+
+impl error::Error for InvalidUidLength {
+    fn description(&self) -> &str {
+        "invalid UID length"
+    }
+}
+
+/// Returned when PN532 can't emulate ISO14443A card with given UID.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UnsupportedEmulatedUid {
+    /// Only single size UID (4 bytes) can be emulated, contains the actual length.
+    Length(usize),
+    /// The first byte is always 0x08, contains the requested one.
+    FirstByte(u8),
+}
+
+impl fmt::Display for UnsupportedEmulatedUid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnsupportedEmulatedUid::Length(len) => write!(f, "PN532 can emulate only 4 byte UID, not {} bytes", len),
+            UnsupportedEmulatedUid::FirstByte(b) => write!(f, "emulated UID must start with 0x08, not 0x{:02X}", b),
+        }
+    }
+}
+
+impl error::Error for UnsupportedEmulatedUid {
+    fn description(&self) -> &str {
+        "unsupported emulated UID"
+    }
+}
+
+/// Returned when PN532 can't activate requested number of tags of given type at once.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UnsupportedTagLimit(pub u8);
+
+impl fmt::Display for UnsupportedTagLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PN532 can't activate {} tags of this type at once", self.0)
+    }
+}
+
+impl error::Error for UnsupportedTagLimit {
+    fn description(&self) -> &str {
+        "unsupported number of tags"
+    }
+}
+
+/// Returned when FeliCa time slot number isn't one of 0, 1, 3, 7 or 15.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InvalidTimeSlotNumber(pub u8);
+
+impl fmt::Display for InvalidTimeSlotNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid time slot number {}, expected 0, 1, 3, 7 or 15", self.0)
+    }
+}
+
+impl error::Error for InvalidTimeSlotNumber {
+    fn description(&self) -> &str {
+        "invalid time slot number"
+    }
+}
+
+/// Returned when target number isn't 1 or 2, the only numbers PN532 assigns.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InvalidTargetNumber(pub u8);
+
+impl fmt::Display for InvalidTargetNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid target number {}, expected 1 or 2", self.0)
+    }
+}
+
+impl error::Error for InvalidTargetNumber {
+    fn description(&self) -> &str {
+        "invalid target number"
+    }
+}
+
+/// Returned by `Tag::write_trailer`, the sector number is checked before anything is written.
+#[derive(Debug)]
+pub enum WriteTrailerError<E> {
+    /// The sector is greater than 39, the last sector of MIFARE Classic 4K.
+    InvalidSector(u8),
+    TransceiveError(E),
+}
+
+impl<E> From<E> for WriteTrailerError<E> {
+    fn from(e: E) -> Self {
+        WriteTrailerError::TransceiveError(e)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for WriteTrailerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WriteTrailerError::InvalidSector(sector) => write!(f, "MIFARE Classic has at most 40 sectors, sector {} requested", sector),
+            WriteTrailerError::TransceiveError(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: error::Error> error::Error for WriteTrailerError<E> {
+    fn description(&self) -> &str {
+        "writing sector trailer failed"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            WriteTrailerError::InvalidSector(_) => None,
+            WriteTrailerError::TransceiveError(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{CommError, Pn532Status, WaitError, DataError, ChecksumType};
+    use ::std::io;
+    use ::std::time::Duration;
+
+    #[test]
+    fn into_io_error() {
+        let err: io::Error = CommError::<io::Error, io::Error>::Status(Pn532Status::Timeout).into();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), CommError::<io::Error, io::Error>::Status(Pn532Status::Timeout).to_string());
+
+        let err: io::Error = WaitError::<io::Error>::Timeout(Duration::from_millis(10)).into();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        let err: io::Error = WaitError::<io::Error>::OtherError(io::Error::other("bus")).into();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn checksum_display() {
+        let err = DataError::InvalidChecksum { which: ChecksumType::Data, received: 0x01, expected: 0x2B };
+        assert_eq!(err.to_string(), "packet data has invalid checksum (received 0x01, expected 0x2B)");
+    }
+}
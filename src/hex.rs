@@ -0,0 +1,65 @@
+//! Formatting of UIDs and frames as hexadecimal without allocation.
+
+use ::core::fmt;
+
+const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Writes `bytes` into `out` as uppercase hexadecimal digits and returns the number of digits written.
+///
+/// Only whole bytes are written, so if `out` is too short, the output is cut
+/// after the last byte which fits.
+pub fn write_hex(out: &mut [u8], bytes: &[u8]) -> usize {
+    let mut len = 0;
+    for (digits, byte) in out.chunks_exact_mut(2).zip(bytes) {
+        digits[0] = DIGITS[(byte >> 4) as usize];
+        digits[1] = DIGITS[(byte & 0x0F) as usize];
+        len += 2;
+    }
+    len
+}
+
+/// Displays the bytes as uppercase hexadecimal digits, e.g. `Hex(tag.id())`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Same as `write_hex`, but returns the digits as `String`.
+#[cfg(feature = "std")]
+pub fn to_hex_string(bytes: &[u8]) -> String {
+    Hex(bytes).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write_hex, Hex};
+
+    #[test]
+    fn hex() {
+        let mut out = [0u8; 8];
+        assert_eq!(write_hex(&mut out, &[0xDE, 0xAD, 0x0B, 0xEF]), 8);
+        assert_eq!(&out, b"DEAD0BEF");
+
+        let mut out = [b'-'; 5];
+        assert_eq!(write_hex(&mut out, &[0x01, 0x23, 0x45]), 4);
+        assert_eq!(&out, b"0123-");
+
+        assert_eq!(Hex(&[0x04, 0xA1]).to_string(), "04A1");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hex_string() {
+        use super::to_hex_string;
+
+        assert_eq!(to_hex_string(&[]), "");
+        assert_eq!(to_hex_string(&[0x04, 0xA1]), "04A1");
+    }
+}
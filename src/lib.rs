@@ -1,35 +1,82 @@
 //! Crate for communication with PN532 (NFC chip by NXP)
+//!
+//! Start with `Unconfigured::new`, which allows working with tags only after SAM was configured.
+//! `PN532::new` is the unchecked escape hatch, it doesn't enforce the ordering.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(any(feature = "std", test))]
 extern crate core;
 
 #[cfg(feature = "with_i2c")]
 extern crate i2cdev;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
 #[cfg(test)]
 #[macro_use]
 extern crate assert_matches;
 
+#[cfg(all(test, feature = "std"))]
+#[macro_use]
+extern crate proptest;
+
 pub mod error;
+pub mod hex;
 pub mod bus;
 mod device;
 
-pub use device::{PN532, SAMMode};
+#[cfg(all(feature = "std", feature = "tags"))]
+pub use device::sync::SyncPN532;
+#[cfg(feature = "std")]
+pub use device::TraceFn;
+pub use device::configured::{Unconfigured, Configured, ConfigureError};
+pub use device::registers;
+pub use device::executor;
+pub use device::{PN532, Frame, ResponseHeader, ProtoStats, Direction, MAX_PREAMBLE_LEN, FRAME_BUF_LEN, DEFAULT_MAX_SCAN_LEN, SAMMode, SamTimeout, FirmwareVersion, IcKind, Quirks, Nfcid3, MifareParams, IsoDepState, Baudrate, TargetStatus, GeneralStatus, SamStatus, AntennaThresholds, HighCurrentThreshold, LowCurrentThreshold, AntennaHealth, ModuleHealth, RxSensitivity, RfErrorFlags};
 
+#[cfg(feature = "tags")]
 pub mod tags {
     pub use ::device::tags_internal::{
         TagBuffer,
         Tags,
+        TagInfos,
+        TagSummary,
         Tag,
         TagNumLimit,
         ISO14443A,
         ISO14443AListOptions,
+        Jewel,
+        JewelTagListOptions,
+        AutoPollOptions,
+        AutoPolled,
+        PollTarget,
+        BaudType,
+        Protocol,
+        CascadeLevel,
+        Uid,
+        Ats,
+        MifareKeyType,
+        AccessBits,
+        SectorTrailer,
+        FeliCa,
+        FeliCaBaudrate,
+        FeliCaListOptions,
+        FeliCaPolling,
+        FeliCaRequestCode,
+        FeliCaTimeSlots,
         /*
         PollingMethod,
         ISO14443BListOptions,
-        FeliCaBaudrate,
-        FeliCaListOptions,
-        JewelTagListOptions
         */
     };
     pub use ::device::tags_internal::TagResponseMarker as TagResponse;
+    #[cfg(feature = "std")]
+    pub use ::device::tags_internal::HeapTagBuffer;
+    #[cfg(feature = "std")]
+    pub use ::device::tags_internal::TagSnapshot;
+    #[cfg(feature = "std")]
+    pub use ::device::tags_internal::DetectedCard;
 }